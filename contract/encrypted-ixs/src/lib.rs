@@ -27,146 +27,202 @@ pub mod circuits {
     // CONFIDENTIAL FUND POOL CIRCUITS
     // ========================================================================
 
-    pub struct DepositInput {
-        pub current_balance: u64,
-        pub deposit_amount: u64,
-    }
-
-    pub struct DepositResult {
-        pub new_balance: u64,
-        pub success: bool,
-    }
-
-    pub struct WithdrawInput {
-        pub current_balance: u64,
-        pub withdraw_amount: u64,
-    }
-
-    pub struct WithdrawResult {
-        pub new_balance: u64,
-        pub success: bool,
-        pub error_code: u8,
-    }
-
-    pub struct TransferInput {
-        pub user_balance: u64,
-        pub pool_balance: u64,
-        pub transfer_amount: u64,
-    }
-
-    pub struct TransferResult {
-        pub new_user_balance: u64,
-        pub new_pool_balance: u64,
-        pub transfer_success: bool,
-    }
-
-    pub struct ShareInput {
-        pub user_contribution: u64,
-        pub total_pool: u64,
-        pub final_amount: u64,
-    }
-
-    pub struct ShareResult {
-        pub user_share: u64,
-        pub computed_successfully: bool,
+    /// Running contribution-weighted totals for one confidential proposal
+    pub struct VoteTally {
+        pub yes_weight: u64,
+        pub no_weight: u64,
     }
 
     // ========================================================================
     // ENCRYPTED INSTRUCTIONS (MPC COMPUTATIONS)
     // ========================================================================
 
+    // User balances are stored on-chain as Enc<Shared, u64> so their owner can
+    // decrypt them; pool totals as Enc<Mxe, u64> so only the cluster can.
+    // The `*_initialized` flags are false until the first MPC write, in which
+    // case the stored ciphertext is ignored and the value is treated as zero.
+    // A pool's participant count is written alongside its total and shares
+    // its flag.
+
     #[instruction]
     pub fn add_to_balance(
-        input_ctxt: Enc<Shared, DepositInput>,
-    ) -> Enc<Shared, DepositResult> {
-        let input = input_ctxt.to_arcis();
-
-        // Simple addition (MPC handles overflow checking)
-        let new_balance = input.current_balance + input.deposit_amount;
-
-        let result = DepositResult {
-            new_balance,
-            success: true,
+        balance_ctxt: Enc<Shared, u64>,
+        balance_initialized: bool,
+        deposit_amount: u64,
+    ) -> Enc<Shared, u64> {
+        let balance = if balance_initialized {
+            balance_ctxt.to_arcis()
+        } else {
+            0
         };
 
-        input_ctxt.owner.from_arcis(result)
+        // Simple addition (MPC handles overflow checking)
+        balance_ctxt.owner.from_arcis(balance + deposit_amount)
     }
 
+    // The transfer circuits also reveal the amount they authorized (zero when
+    // the balance check fails), so the callback moves exactly what was
+    // debited from the encrypted ledger. Pool transfers also keep the
+    // member's own contribution to the pool, `Enc<Mxe, u64>`, in step; it
+    // weighs their confidential votes.
+
     #[instruction]
     pub fn subtract_from_balance(
-        input_ctxt: Enc<Shared, WithdrawInput>,
-    ) -> Enc<Shared, WithdrawResult> {
-        let input = input_ctxt.to_arcis();
-
-        let has_sufficient = input.current_balance >= input.withdraw_amount;
-
-        let result = if has_sufficient {
-            WithdrawResult {
-                new_balance: input.current_balance - input.withdraw_amount,
-                success: true,
-                error_code: 0,
-            }
+        balance_ctxt: Enc<Shared, u64>,
+        balance_initialized: bool,
+        amount_ctxt: Enc<Shared, u64>,
+    ) -> (Enc<Shared, u64>, bool, u64) {
+        let balance = if balance_initialized {
+            balance_ctxt.to_arcis()
         } else {
-            WithdrawResult {
-                new_balance: input.current_balance,
-                success: false,
-                error_code: 1,
-            }
+            0
         };
+        let amount = amount_ctxt.to_arcis();
+
+        let has_sufficient = balance >= amount;
+        let new_balance = if has_sufficient { balance - amount } else { balance };
+        let withdrawn = if has_sufficient { amount } else { 0 };
 
-        input_ctxt.owner.from_arcis(result)
+        (
+            balance_ctxt.owner.from_arcis(new_balance),
+            has_sufficient.reveal(),
+            withdrawn.reveal(),
+        )
     }
 
     #[instruction]
     pub fn confidential_transfer_to_pool(
-        input_ctxt: Enc<Shared, TransferInput>,
-    ) -> Enc<Shared, TransferResult> {
-        let input = input_ctxt.to_arcis();
+        user_balance_ctxt: Enc<Shared, u64>,
+        user_balance_initialized: bool,
+        pool_total_ctxt: Enc<Mxe, u64>,
+        pool_total_initialized: bool,
+        participants_ctxt: Enc<Mxe, u64>,
+        contribution_ctxt: Enc<Mxe, u64>,
+        contribution_initialized: bool,
+        amount_ctxt: Enc<Shared, u64>,
+    ) -> (Enc<Shared, u64>, Enc<Mxe, u64>, bool, u64, Enc<Mxe, u64>, Enc<Mxe, u64>) {
+        let user_balance = if user_balance_initialized {
+            user_balance_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let (pool_total, participants) = if pool_total_initialized {
+            (pool_total_ctxt.to_arcis(), participants_ctxt.to_arcis())
+        } else {
+            (0, 0)
+        };
+        let contribution = if contribution_initialized {
+            contribution_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let amount = amount_ctxt.to_arcis();
 
         // Check balance without assert (not supported)
-        let has_sufficient = input.user_balance >= input.transfer_amount;
-
-        let result = if has_sufficient {
-            TransferResult {
-                new_user_balance: input.user_balance - input.transfer_amount,
-                new_pool_balance: input.pool_balance + input.transfer_amount,
-                transfer_success: true,
-            }
+        let has_sufficient = user_balance >= amount;
+        let (new_user_balance, new_pool_total) = if has_sufficient {
+            (user_balance - amount, pool_total + amount)
         } else {
-            TransferResult {
-                new_user_balance: input.user_balance,
-                new_pool_balance: input.pool_balance,
-                transfer_success: false,
-            }
+            (user_balance, pool_total)
         };
 
-        input_ctxt.owner.from_arcis(result)
+        let transferred = if has_sufficient { amount } else { 0 };
+
+        // A member counts from their first contribution, however many follow
+        let has_joined = contribution == 0 && transferred > 0;
+        let new_participants = if has_joined { participants + 1 } else { participants };
+
+        (
+            user_balance_ctxt.owner.from_arcis(new_user_balance),
+            pool_total_ctxt.owner.from_arcis(new_pool_total),
+            has_sufficient.reveal(),
+            transferred.reveal(),
+            contribution_ctxt.owner.from_arcis(contribution + transferred),
+            participants_ctxt.owner.from_arcis(new_participants),
+        )
     }
 
     #[instruction]
     pub fn confidential_transfer_from_pool(
-        input_ctxt: Enc<Shared, TransferInput>,
-    ) -> Enc<Shared, TransferResult> {
-        let input = input_ctxt.to_arcis();
+        user_balance_ctxt: Enc<Shared, u64>,
+        user_balance_initialized: bool,
+        pool_total_ctxt: Enc<Mxe, u64>,
+        pool_total_initialized: bool,
+        participants_ctxt: Enc<Mxe, u64>,
+        contribution_ctxt: Enc<Mxe, u64>,
+        contribution_initialized: bool,
+        amount_ctxt: Enc<Shared, u64>,
+    ) -> (Enc<Shared, u64>, Enc<Mxe, u64>, bool, u64, Enc<Mxe, u64>, Enc<Mxe, u64>) {
+        let user_balance = if user_balance_initialized {
+            user_balance_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let (pool_total, participants) = if pool_total_initialized {
+            (pool_total_ctxt.to_arcis(), participants_ctxt.to_arcis())
+        } else {
+            (0, 0)
+        };
+        let contribution = if contribution_initialized {
+            contribution_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let amount = amount_ctxt.to_arcis();
+
+        // A member can only take out what they put in: dark pools never
+        // trade, so contributions must keep summing to the pool total for
+        // `compute_user_share` to pay everyone pro rata
+        let has_sufficient = contribution >= amount && pool_total >= amount;
+        let (new_user_balance, new_pool_total) = if has_sufficient {
+            (user_balance + amount, pool_total - amount)
+        } else {
+            (user_balance, pool_total)
+        };
 
-        // Check balance without assert
-        let has_sufficient = input.pool_balance >= input.transfer_amount;
+        let transferred = if has_sufficient { amount } else { 0 };
+        let new_contribution = contribution - transferred;
+
+        // A member stops counting once their contribution is used up
+        let has_left = contribution > 0 && new_contribution == 0 && participants > 0;
+        let new_participants = if has_left { participants - 1 } else { participants };
+
+        (
+            user_balance_ctxt.owner.from_arcis(new_user_balance),
+            pool_total_ctxt.owner.from_arcis(new_pool_total),
+            has_sufficient.reveal(),
+            transferred.reveal(),
+            contribution_ctxt.owner.from_arcis(new_contribution),
+            participants_ctxt.owner.from_arcis(new_participants),
+        )
+    }
 
-        let result = if has_sufficient {
-            TransferResult {
-                new_user_balance: input.user_balance + input.transfer_amount,
-                new_pool_balance: input.pool_balance - input.transfer_amount,
-                transfer_success: true,
-            }
+    /// Pool health from its encrypted state: the total (the sum of every
+    /// member balance moved in) and the participant count the transfer
+    /// circuits keep. Reveals `(participant_count, has_minimum_participants,
+    /// pool_is_healthy)`, never the total or any member's share of it.
+    #[instruction]
+    pub fn compute_aggregate_stats(
+        pool_total_ctxt: Enc<Mxe, u64>,
+        pool_total_initialized: bool,
+        participants_ctxt: Enc<Mxe, u64>,
+        minimum_participants: u32,
+        minimum_total: u64,
+    ) -> (u32, bool, bool) {
+        let (pool_total, participants) = if pool_total_initialized {
+            (pool_total_ctxt.to_arcis(), participants_ctxt.to_arcis())
         } else {
-            TransferResult {
-                new_user_balance: input.user_balance,
-                new_pool_balance: input.pool_balance,
-                transfer_success: false,
-            }
+            (0, 0)
         };
 
-        input_ctxt.owner.from_arcis(result)
+        let has_minimum_participants = participants >= minimum_participants as u64;
+        let pool_is_healthy = pool_total >= minimum_total;
+
+        (
+            (participants as u32).reveal(),
+            has_minimum_participants.reveal(),
+            pool_is_healthy.reveal(),
+        )
     }
 
     #[instruction]
@@ -179,24 +235,136 @@ pub mod circuits {
         input_ctxt.owner.from_arcis(meets_minimum)
     }
 
+    /// Pay a member out of a closed pool in proportion to their contribution:
+    /// `contribution * final_amount / pool_total`. Their contribution leaves
+    /// the total as the share leaves the vault, so members settling one after
+    /// another each get the same fraction of what was there at close.
+    /// Reveals only the share, which is about to move as a public transfer.
     #[instruction]
     pub fn compute_user_share(
-        input_ctxt: Enc<Shared, ShareInput>,
-    ) -> Enc<Shared, ShareResult> {
-        let input = input_ctxt.to_arcis();
+        user_balance_ctxt: Enc<Shared, u64>,
+        user_balance_initialized: bool,
+        pool_total_ctxt: Enc<Mxe, u64>,
+        pool_total_initialized: bool,
+        participants_ctxt: Enc<Mxe, u64>,
+        contribution_ctxt: Enc<Mxe, u64>,
+        contribution_initialized: bool,
+        final_amount: u64,
+    ) -> (Enc<Shared, u64>, Enc<Mxe, u64>, Enc<Mxe, u64>, u64, Enc<Mxe, u64>) {
+        let user_balance = if user_balance_initialized {
+            user_balance_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let (pool_total, participants) = if pool_total_initialized {
+            (pool_total_ctxt.to_arcis(), participants_ctxt.to_arcis())
+        } else {
+            (0, 0)
+        };
+        let contribution = if contribution_initialized {
+            contribution_ctxt.to_arcis()
+        } else {
+            0
+        };
 
-        let share = if input.total_pool > 0 {
-            (input.user_contribution as u128 * input.final_amount as u128
-                / input.total_pool as u128) as u64
+        let share = if pool_total > 0 {
+            (contribution as u128 * final_amount as u128 / pool_total as u128) as u64
+        } else {
+            0
+        };
+        // Never more than the vault holds, even if the stored values disagree
+        let share = if share > final_amount { final_amount } else { share };
+        let new_pool_total = if pool_total >= contribution {
+            pool_total - contribution
         } else {
             0
         };
+        let has_left = contribution > 0 && participants > 0;
+        let new_participants = if has_left { participants - 1 } else { participants };
+
+        (
+            user_balance_ctxt.owner.from_arcis(user_balance + share),
+            pool_total_ctxt.owner.from_arcis(new_pool_total),
+            contribution_ctxt.owner.from_arcis(0u64),
+            share.reveal(),
+            participants_ctxt.owner.from_arcis(new_participants),
+        )
+    }
 
-        let result = ShareResult {
-            user_share: share,
-            computed_successfully: true,
+    // ========================================================================
+    // CONFIDENTIAL VOTING
+    // ========================================================================
+
+    /// Add one member's vote to a proposal's encrypted tally, weighted by
+    /// their encrypted pool contribution. Neither the vote nor the weight is
+    /// revealed.
+    #[instruction]
+    pub fn add_weighted_vote(
+        vote_ctxt: Enc<Shared, bool>,
+        contribution_ctxt: Enc<Mxe, u64>,
+        contribution_initialized: bool,
+        tally_ctxt: Enc<Mxe, VoteTally>,
+        tally_initialized: bool,
+    ) -> Enc<Mxe, VoteTally> {
+        let approve = vote_ctxt.to_arcis();
+        let weight = if contribution_initialized {
+            contribution_ctxt.to_arcis()
+        } else {
+            0
+        };
+        let mut tally = if tally_initialized {
+            tally_ctxt.to_arcis()
+        } else {
+            VoteTally {
+                yes_weight: 0,
+                no_weight: 0,
+            }
+        };
+
+        if approve {
+            tally.yes_weight += weight;
+        } else {
+            tally.no_weight += weight;
+        }
+
+        tally_ctxt.owner.from_arcis(tally)
+    }
+
+    /// Close a proposal: passed if turnout reaches `quorum_bps` of the pool
+    /// total when voting opened and yes outweighs no. Reveals only
+    /// `(passed, turnout_bps)`, never the weights behind them. Turnout is
+    /// not capped, so a tally above the snapshot shows up as more than
+    /// 10_000 bps.
+    #[instruction]
+    pub fn tally_votes(
+        tally_ctxt: Enc<Mxe, VoteTally>,
+        tally_initialized: bool,
+        pool_total_ctxt: Enc<Mxe, u64>,
+        pool_total_initialized: bool,
+        quorum_bps: u16,
+    ) -> (bool, u64) {
+        let tally = if tally_initialized {
+            tally_ctxt.to_arcis()
+        } else {
+            VoteTally {
+                yes_weight: 0,
+                no_weight: 0,
+            }
+        };
+        let pool_total = if pool_total_initialized {
+            pool_total_ctxt.to_arcis()
+        } else {
+            0
+        };
+
+        let turnout = tally.yes_weight as u128 + tally.no_weight as u128;
+        let turnout_bps = if pool_total > 0 {
+            (turnout * 10_000 / pool_total as u128) as u64
+        } else {
+            0
         };
+        let passed = turnout_bps >= quorum_bps as u64 && tally.yes_weight > tally.no_weight;
 
-        input_ctxt.owner.from_arcis(result)
+        (passed.reveal(), turnout_bps.reveal())
     }
 }
@@ -0,0 +1,101 @@
+// MOCK AMM
+// Stand-in DEX for the pool swap tests. Swaps at whatever rate the caller
+// asks for, paying out of reserves owned by its `reserve_authority` PDA.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    self, Approve, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+declare_id!("FcrZ4szBqgMkvckF5G4SSCfBsfuPjaUkC3oCsxB94Qyh");
+
+#[program]
+pub mod mock_amm {
+    use super::*;
+
+    /// Take `amount_in` from the user and pay them `amount_out`
+    pub fn swap(ctx: Context<Swap>, amount_in: u64, amount_out: u64) -> Result<()> {
+        execute_swap(&ctx, amount_in, amount_out)
+    }
+
+    /// A misbehaving swap: trades as asked, then uses the user's signature
+    /// to make the reserve authority a delegate over their source account
+    pub fn swap_and_approve(ctx: Context<Swap>, amount_in: u64, amount_out: u64) -> Result<()> {
+        execute_swap(&ctx, amount_in, amount_out)?;
+        token_interface::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Approve {
+                    to: ctx.accounts.user_source.to_account_info(),
+                    delegate: ctx.accounts.reserve_authority.to_account_info(),
+                    authority: ctx.accounts.user_authority.to_account_info(),
+                },
+            ),
+            u64::MAX,
+        )
+    }
+}
+
+fn execute_swap(ctx: &Context<Swap>, amount_in: u64, amount_out: u64) -> Result<()> {
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_source.to_account_info(),
+                mint: ctx.accounts.mint_in.to_account_info(),
+                to: ctx.accounts.reserve_in.to_account_info(),
+                authority: ctx.accounts.user_authority.to_account_info(),
+            },
+        ),
+        amount_in,
+        ctx.accounts.mint_in.decimals,
+    )?;
+
+    let seeds = &[
+        b"reserve_authority".as_ref(),
+        &[ctx.bumps.reserve_authority],
+    ];
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.reserve_out.to_account_info(),
+                mint: ctx.accounts.mint_out.to_account_info(),
+                to: ctx.accounts.user_destination.to_account_info(),
+                authority: ctx.accounts.reserve_authority.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount_out,
+        ctx.accounts.mint_out.decimals,
+    )?;
+
+    msg!("Mock swap: {} in, {} out", amount_in, amount_out);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    pub user_authority: Signer<'info>,
+
+    #[account(mut)]
+    pub user_source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_destination: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reserve_out: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Owns the reserves, only signs
+    #[account(seeds = [b"reserve_authority"], bump)]
+    pub reserve_authority: UncheckedAccount<'info>,
+
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
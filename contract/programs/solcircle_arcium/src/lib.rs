@@ -5,23 +5,59 @@
 // Deploy: arcium deploy
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::{
+    ed25519_program,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    sysvar::instructions as ix_sysvar,
+};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_hook::TransferHook, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
 
 // Arcium imports for confidential computing
 use arcium_anchor::prelude::*;
-use arcium_client::prelude::*;
-
-// Import encrypted circuits
-use encrypted_ixs::circuits::{
-    DepositInput, DepositResult, WithdrawInput, WithdrawResult,
-    TransferInput, TransferResult, AggregateInput, AggregateStats,
-};
+use arcium_client::idl::arcium::types::CallbackAccount;
 
 declare_id!("CXsaoofHepCa6JT197g6PpzviF4H4adHrjRmekwT5vtL");
 
-/// Arcium MPC Authority - will be provided by Arcium network
-/// Replace with actual pubkey from Arcium documentation
-const ARCIUM_MPC_AUTHORITY: Pubkey = solana_program::pubkey!("11111111111111111111111111111111");
+// Computation definition offsets, one per encrypted instruction
+const COMP_DEF_OFFSET_ADD_TO_BALANCE: u32 = comp_def_offset("add_to_balance");
+const COMP_DEF_OFFSET_SUBTRACT_FROM_BALANCE: u32 = comp_def_offset("subtract_from_balance");
+const COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_TO_POOL: u32 =
+    comp_def_offset("confidential_transfer_to_pool");
+const COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_FROM_POOL: u32 =
+    comp_def_offset("confidential_transfer_from_pool");
+const COMP_DEF_OFFSET_VERIFY_MINIMUM_BALANCE: u32 = comp_def_offset("verify_minimum_balance");
+const COMP_DEF_OFFSET_COMPUTE_USER_SHARE: u32 = comp_def_offset("compute_user_share");
+const COMP_DEF_OFFSET_COMPUTE_AGGREGATE_STATS: u32 = comp_def_offset("compute_aggregate_stats");
+const COMP_DEF_OFFSET_ADD_WEIGHTED_VOTE: u32 = comp_def_offset("add_weighted_vote");
+const COMP_DEF_OFFSET_TALLY_VOTES: u32 = comp_def_offset("tally_votes");
+
+/// Maximum number of oracle keys in the M-of-N signer set
+pub const MAX_ORACLES: usize = 5;
+
+/// Delay before a proposed oracle key or authority takes effect (~1 day)
+pub const ORACLE_TIMELOCK_SLOTS: u64 = 216_000;
+
+/// Default slots a queued MPC computation may stay pending (~10 minutes)
+pub const DEFAULT_MPC_TIMEOUT_SLOTS: u64 = 1_500;
+
+/// Participants a dark pool needs before it counts as meeting its minimum
+pub const MIN_POOL_PARTICIPANTS: u32 = 5;
+
+/// Maximum number of programs `execute_pool_swap` may route through
+pub const MAX_SWAP_PROGRAMS: usize = 8;
+
+/// Maximum number of tokens a public pool may hold at once besides its mint
+pub const MAX_POOL_POSITIONS: usize = 8;
 
 #[arcium_program]
 pub mod sol_circle {
@@ -32,20 +68,148 @@ pub mod sol_circle {
     // ========================================================================
 
     /// Initialize oracle configuration (admin only, called once)
-    pub fn initialize_oracle(ctx: Context<InitializeOracle>) -> Result<()> {
+    pub fn initialize_oracle(ctx: Context<InitializeOracle>, cluster: OracleCluster) -> Result<()> {
         let oracle_config = &mut ctx.accounts.oracle_config;
         oracle_config.authority = ctx.accounts.authority.key();
-        oracle_config.oracle_pubkey = ctx.accounts.oracle_pubkey.key();
+        oracle_config.oracle_keys = vec![ctx.accounts.oracle_pubkey.key()];
+        oracle_config.threshold = 1;
+        oracle_config.pending_oracle = None;
+        oracle_config.oracle_activation_slot = 0;
+        oracle_config.pending_authority = None;
+        oracle_config.authority_activation_slot = 0;
+        oracle_config.cluster = cluster;
+        oracle_config.mpc_timeout_slots = DEFAULT_MPC_TIMEOUT_SLOTS;
         oracle_config.bump = ctx.bumps.oracle_config;
 
-        msg!("Oracle initialized: {}", oracle_config.oracle_pubkey);
+        msg!("Oracle initialized: {}", ctx.accounts.oracle_pubkey.key());
+        Ok(())
+    }
+
+    /// Propose adding an oracle key (authority only). The key can be accepted
+    /// once ORACLE_TIMELOCK_SLOTS have passed, giving pool members time to exit.
+    pub fn propose_oracle(ctx: Context<UpdateOracle>, oracle_pubkey: Pubkey) -> Result<()> {
+        propose_oracle_change(&mut ctx.accounts.oracle_config, OracleChange::Add(oracle_pubkey))
+    }
+
+    /// Propose removing an oracle key from the signer set (authority only),
+    /// behind the same timelock as adding one
+    pub fn propose_oracle_removal(ctx: Context<UpdateOracle>, oracle_pubkey: Pubkey) -> Result<()> {
+        propose_oracle_change(
+            &mut ctx.accounts.oracle_config,
+            OracleChange::Remove(oracle_pubkey),
+        )
+    }
+
+    /// Propose how many distinct oracle signatures an authorization needs
+    /// (authority only), behind the same timelock as adding a key
+    pub fn propose_oracle_threshold(ctx: Context<UpdateOracle>, threshold: u8) -> Result<()> {
+        propose_oracle_change(
+            &mut ctx.accounts.oracle_config,
+            OracleChange::SetThreshold(threshold),
+        )
+    }
+
+    /// Apply the pending oracle change after the timelock (authority only)
+    pub fn accept_oracle(ctx: Context<UpdateOracle>) -> Result<()> {
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        let change = oracle_config
+            .pending_oracle
+            .ok_or(ErrorCode::NoPendingChange)?;
+        require!(
+            Clock::get()?.slot >= oracle_config.oracle_activation_slot,
+            ErrorCode::TimelockNotElapsed
+        );
+
+        oracle_config.apply(change)?;
+        oracle_config.pending_oracle = None;
+        oracle_config.oracle_activation_slot = 0;
+
+        emit!(OracleAccepted { change });
+        msg!(
+            "Oracle change applied: {:?} ({} of {})",
+            change,
+            oracle_config.threshold,
+            oracle_config.oracle_keys.len()
+        );
+        Ok(())
+    }
+
+    /// Drop the pending oracle change (authority only)
+    pub fn cancel_oracle_proposal(ctx: Context<UpdateOracle>) -> Result<()> {
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        let change = oracle_config
+            .pending_oracle
+            .take()
+            .ok_or(ErrorCode::NoPendingChange)?;
+        oracle_config.oracle_activation_slot = 0;
+
+        emit!(OracleProposalCancelled { change });
+        msg!("Oracle change cancelled: {:?}", change);
+        Ok(())
+    }
+
+    /// Set how long a queued MPC computation may stay pending before anyone
+    /// can expire it (authority only)
+    pub fn set_mpc_timeout(ctx: Context<UpdateOracle>, timeout_slots: u64) -> Result<()> {
+        require!(timeout_slots > 0, ErrorCode::InvalidAmount);
+        ctx.accounts.oracle_config.mpc_timeout_slots = timeout_slots;
+        msg!("MPC request timeout set: {} slots", timeout_slots);
+        Ok(())
+    }
+
+    /// Propose a new oracle config authority (authority only). The new
+    /// authority can accept once ORACLE_TIMELOCK_SLOTS have passed.
+    pub fn propose_authority(ctx: Context<UpdateOracle>, new_authority: Pubkey) -> Result<()> {
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        require!(
+            oracle_config.pending_authority.is_none(),
+            ErrorCode::ProposalAlreadyPending
+        );
+
+        let activation_slot = Clock::get()?.slot + ORACLE_TIMELOCK_SLOTS;
+        oracle_config.pending_authority = Some(new_authority);
+        oracle_config.authority_activation_slot = activation_slot;
+
+        emit!(AuthorityProposed {
+            new_authority,
+            activation_slot,
+        });
+        msg!("Authority proposed: {} (active from slot {})", new_authority, activation_slot);
+        Ok(())
+    }
+
+    /// Take over as oracle config authority after the timelock (pending authority only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        require!(
+            Clock::get()?.slot >= oracle_config.authority_activation_slot,
+            ErrorCode::TimelockNotElapsed
+        );
+
+        let previous_authority = oracle_config.authority;
+        oracle_config.authority = ctx.accounts.new_authority.key();
+        oracle_config.pending_authority = None;
+        oracle_config.authority_activation_slot = 0;
+
+        emit!(AuthorityAccepted {
+            previous_authority,
+            new_authority: oracle_config.authority,
+        });
+        msg!("Authority transferred: {} -> {}", previous_authority, oracle_config.authority);
         Ok(())
     }
 
-    /// Update oracle public key (authority only)
-    pub fn update_oracle(ctx: Context<UpdateOracle>, new_oracle_pubkey: Pubkey) -> Result<()> {
-        ctx.accounts.oracle_config.oracle_pubkey = new_oracle_pubkey;
-        msg!("Oracle updated: {}", new_oracle_pubkey);
+    /// Drop the pending authority transfer (authority only)
+    pub fn cancel_authority_transfer(ctx: Context<UpdateOracle>) -> Result<()> {
+        let oracle_config = &mut ctx.accounts.oracle_config;
+        let new_authority = oracle_config
+            .pending_authority
+            .take()
+            .ok_or(ErrorCode::NoPendingChange)?;
+        oracle_config.authority_activation_slot = 0;
+
+        emit!(AuthorityTransferCancelled { new_authority });
+        msg!("Authority transfer cancelled: {}", new_authority);
         Ok(())
     }
 
@@ -57,8 +221,12 @@ pub mod sol_circle {
     pub fn initialize_user_deposit(ctx: Context<InitializeUserDeposit>) -> Result<()> {
         let user_deposit = &mut ctx.accounts.user_deposit;
         user_deposit.owner = ctx.accounts.user.key();
+        user_deposit.mint = ctx.accounts.mint.key();
         user_deposit.deposited_amount = 0;
+        user_deposit.oracle_nonce = 0;
         user_deposit.bump = ctx.bumps.user_deposit;
+        user_deposit.vault_bump = ctx.bumps.user_vault;
+        check_mint_extensions(&ctx.accounts.mint, true)?;
 
         msg!("User deposit initialized: {}", user_deposit.owner);
         Ok(())
@@ -68,21 +236,20 @@ pub mod sol_circle {
     pub fn deposit_to_user_pda(ctx: Context<DepositToUserPda>, amount: u64) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
 
-        // Transfer tokens from user to their PDA vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.user_vault.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
+        // Transfer tokens from user to their PDA vault, crediting only what
+        // arrives after any transfer fee
+        let received = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_token_account,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.user.to_account_info(),
+            &[],
             amount,
         )?;
 
-        ctx.accounts.user_deposit.deposited_amount += amount;
-        msg!("User {} deposited {} (PUBLIC)", ctx.accounts.user.key(), amount);
+        ctx.accounts.user_deposit.deposited_amount += received;
+        msg!("User {} deposited {} (PUBLIC)", ctx.accounts.user.key(), received);
         Ok(())
     }
 
@@ -90,7 +257,8 @@ pub mod sol_circle {
     pub fn withdraw_from_user_pda(
         ctx: Context<WithdrawFromUserPda>,
         amount: u64,
-        oracle_signature: [u8; 64],
+        nonce: u64,
+        expiry_slot: u64,
     ) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
         require!(
@@ -98,36 +266,39 @@ pub mod sol_circle {
             ErrorCode::InsufficientBalance
         );
 
-        // Verify oracle signature
-        verify_oracle_signature(
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::Withdraw,
+            ctx.accounts.user.key(),
+            Pubkey::default(),
+            ctx.accounts.user_vault.mint,
+            OracleAmount::Plain(amount),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
             &ctx.accounts.oracle_config,
-            format!(
-                "withdraw:{}:{}:{}",
-                ctx.accounts.user.key(),
-                amount,
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.user_deposit.oracle_nonce,
         )?;
 
         // Transfer tokens from PDA vault back to user
         let seeds = &[
             b"user_vault",
             ctx.accounts.user.key().as_ref(),
-            &[ctx.accounts.user_deposit.bump],
+            ctx.accounts.user_deposit.mint.as_ref(),
+            &[ctx.accounts.user_deposit.vault_bump],
         ];
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_vault.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.user_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &mut ctx.accounts.user_token_account,
+            &ctx.accounts.mint,
+            ctx.accounts.user_vault.to_account_info(),
+            &[&seeds[..]],
             amount,
         )?;
 
@@ -136,6 +307,92 @@ pub mod sol_circle {
         Ok(())
     }
 
+    /// Deposit native SOL to the user's wSOL deposit, wrapping it in the vault
+    pub fn deposit_sol_to_user_pda(ctx: Context<DepositSolToUserPda>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, ErrorCode::InvalidAmount);
+
+        wrap_sol(
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.user_vault.to_account_info(),
+            lamports,
+        )?;
+
+        ctx.accounts.user_deposit.deposited_amount += lamports;
+        msg!("User {} deposited {} lamports (PUBLIC)", ctx.accounts.user.key(), lamports);
+        Ok(())
+    }
+
+    /// Withdraw from the user's wSOL deposit as native SOL, with oracle signature
+    ///
+    /// The wSOL is moved into a short-lived unwrap account, which is then
+    /// closed to the user so they receive plain lamports.
+    pub fn withdraw_sol_from_user_pda(
+        ctx: Context<WithdrawSolFromUserPda>,
+        amount: u64,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            amount <= ctx.accounts.user_deposit.deposited_amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::Withdraw,
+            ctx.accounts.user.key(),
+            Pubkey::default(),
+            ctx.accounts.user_vault.mint,
+            OracleAmount::Plain(amount),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
+            &ctx.accounts.oracle_config,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.user_deposit.oracle_nonce,
+        )?;
+
+        let vault_seeds = &[
+            b"user_vault",
+            ctx.accounts.user.key().as_ref(),
+            ctx.accounts.user_deposit.mint.as_ref(),
+            &[ctx.accounts.user_deposit.vault_bump],
+        ];
+
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &mut ctx.accounts.unwrap_account,
+            &ctx.accounts.mint,
+            ctx.accounts.user_vault.to_account_info(),
+            &[&vault_seeds[..]],
+            amount,
+        )?;
+
+        // Closing a native token account releases its wrapped lamports too
+        let user_key = ctx.accounts.user.key();
+        let unwrap_seeds: &[&[u8]] = &[b"sol_unwrap", user_key.as_ref(), &[ctx.bumps.unwrap_account]];
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.unwrap_account.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
+                authority: ctx.accounts.unwrap_account.to_account_info(),
+            },
+            &[unwrap_seeds],
+        ))?;
+
+        ctx.accounts.user_deposit.deposited_amount -= amount;
+        msg!("User {} withdrew {} lamports (PUBLIC)", ctx.accounts.user.key(), amount);
+        Ok(())
+    }
+
     /// Create public group pool
     pub fn create_group_pool(
         ctx: Context<CreateGroupPool>,
@@ -144,11 +401,17 @@ pub mod sol_circle {
     ) -> Result<()> {
         let group_pool = &mut ctx.accounts.group_pool;
         group_pool.admin = ctx.accounts.admin.key();
+        group_pool.mint = ctx.accounts.mint.key();
         group_pool.group_id = group_id.clone();
         group_pool.group_name = group_name;
         group_pool.total_locked = 0;
+        group_pool.total_shares = 0;
+        group_pool.net_asset_value = 0;
         group_pool.is_active = true;
+        group_pool.open_positions = Vec::new();
         group_pool.bump = ctx.bumps.group_pool;
+        group_pool.vault_bump = ctx.bumps.group_vault;
+        check_mint_extensions(&ctx.accounts.mint, true)?;
 
         msg!("Group pool created: {}", group_id);
         Ok(())
@@ -158,7 +421,8 @@ pub mod sol_circle {
     pub fn release_funds_to_group(
         ctx: Context<ReleaseFundsToGroup>,
         amount: u64,
-        oracle_signature: [u8; 64],
+        nonce: u64,
+        expiry_slot: u64,
     ) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
         require!(
@@ -166,42 +430,67 @@ pub mod sol_circle {
             ErrorCode::InsufficientBalance
         );
         require!(ctx.accounts.group_pool.is_active, ErrorCode::PoolNotActive);
+        require!(
+            ctx.accounts.session.accepts_releases(Clock::get()?.slot),
+            ErrorCode::SessionNotOpen
+        );
 
-        verify_oracle_signature(
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::ReleaseToGroup,
+            ctx.accounts.user.key(),
+            ctx.accounts.group_pool.key(),
+            ctx.accounts.user_vault.mint,
+            OracleAmount::Plain(amount),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
             &ctx.accounts.oracle_config,
-            format!(
-                "release_to_group:{}:{}:{}:{}",
-                ctx.accounts.user.key(),
-                ctx.accounts.group_pool.group_id,
-                amount,
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.user_deposit.oracle_nonce,
         )?;
 
-        // Transfer from user vault to group vault
+        // Shares are priced against what the pool held before this release
+        let nav = ctx.accounts.group_pool.nav()?;
+
+        // Transfer from user vault to group vault; the pool is credited net
+        // of any transfer fee
         let seeds = &[
             b"user_vault",
             ctx.accounts.user.key().as_ref(),
-            &[ctx.accounts.user_deposit.bump],
+            ctx.accounts.user_deposit.mint.as_ref(),
+            &[ctx.accounts.user_deposit.vault_bump],
         ];
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_vault.to_account_info(),
-                    to: ctx.accounts.group_vault.to_account_info(),
-                    authority: ctx.accounts.user_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
+        let received = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &mut ctx.accounts.group_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.user_vault.to_account_info(),
+            &[&seeds[..]],
             amount,
         )?;
 
+        let shares = ctx.accounts.group_pool.shares_for_deposit(received, nav)?;
+        require!(shares > 0, ErrorCode::DepositTooSmall);
+
         ctx.accounts.user_deposit.deposited_amount -= amount;
-        ctx.accounts.group_pool.total_locked += amount;
+        ctx.accounts.group_pool.total_locked += received;
+        ctx.accounts.group_pool.total_shares += shares;
+        ctx.accounts.group_pool.net_asset_value += received;
+
+        // Locked contribution doubles as the member's voting weight
+        let position = &mut ctx.accounts.member_position;
+        position.pool = ctx.accounts.group_pool.key();
+        position.member = ctx.accounts.user.key();
+        position.contributed_amount += received;
+        position.shares += shares;
+        position.last_increase_slot = Clock::get()?.slot;
+        position.bump = ctx.bumps.member_position;
 
         msg!(
             "Released {} from user {} to group {} (PUBLIC)",
@@ -216,49 +505,63 @@ pub mod sol_circle {
     pub fn release_funds_from_group(
         ctx: Context<ReleaseFundsFromGroup>,
         amount: u64,
-        oracle_signature: [u8; 64],
+        nonce: u64,
+        expiry_slot: u64,
     ) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
+        let nav = ctx.accounts.group_pool.nav()?;
+        let entitlement = ctx
+            .accounts
+            .group_pool
+            .value_of(ctx.accounts.member_position.shares, nav);
+        require!(amount <= entitlement, ErrorCode::ExceedsEntitlement);
         require!(
-            amount <= ctx.accounts.group_pool.total_locked,
-            ErrorCode::InsufficientBalance
+            ctx.accounts.session.funds_unlocked(Clock::get()?.slot),
+            ErrorCode::SessionLocked
         );
 
-        verify_oracle_signature(
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
             &ctx.accounts.oracle_config,
-            format!(
-                "release_from_group:{}:{}:{}:{}",
-                ctx.accounts.user.key(),
-                ctx.accounts.group_pool.group_id,
-                amount,
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
+            OracleAction::ReleaseFromGroup,
+            ctx.accounts.user.key(),
+            ctx.accounts.group_pool.key(),
+            ctx.accounts.group_vault.mint,
+            OracleAmount::Plain(amount),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
+            &ctx.accounts.oracle_config,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.user_deposit.oracle_nonce,
         )?;
 
-        // Transfer from group vault to user vault
+        // Transfer from group vault to user vault; the user is credited net
+        // of any transfer fee
         let seeds = &[
             b"group_vault",
             ctx.accounts.group_pool.group_id.as_bytes(),
-            &[ctx.accounts.group_pool.bump],
+            ctx.accounts.group_pool.mint.as_ref(),
+            &[ctx.accounts.group_pool.vault_bump],
         ];
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.group_vault.to_account_info(),
-                    to: ctx.accounts.user_vault.to_account_info(),
-                    authority: ctx.accounts.group_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
+        let received = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.group_vault,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.group_vault.to_account_info(),
+            &[&seeds[..]],
             amount,
         )?;
 
-        ctx.accounts.group_pool.total_locked -= amount;
-        ctx.accounts.user_deposit.deposited_amount += amount;
+        let shares = ctx.accounts.group_pool.shares_for_withdrawal(amount, nav);
+        ctx.accounts
+            .group_pool
+            .burn(&mut ctx.accounts.member_position, shares, amount);
+        ctx.accounts.user_deposit.deposited_amount += received;
 
         msg!(
             "Released {} from group {} to user {} (PUBLIC)",
@@ -270,1019 +573,5834 @@ pub mod sol_circle {
     }
 
     // ========================================================================
-    // CONFIDENTIAL OPERATIONS (ARCIUM DARK POOL)
+    // PROPOSALS & VOTING (PUBLIC POOLS)
     // ========================================================================
 
-    /// Initialize confidential user deposit account with encrypted balance
-    pub fn initialize_confidential_deposit(
-        ctx: Context<InitializeConfidentialDeposit>,
+    /// Open a proposal on a public group pool. Only members with locked
+    /// funds can propose, and votes are weighted by locked contribution.
+    ///
+    /// `recipient` is the member paid by a `Release`; it is ignored for
+    /// trades.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_id: u64,
+        action: ProposalAction,
+        token: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        voting_slots: u64,
+        quorum_bps: u16,
     ) -> Result<()> {
-        let conf_deposit = &mut ctx.accounts.confidential_deposit;
-        conf_deposit.owner = ctx.accounts.user.key();
-        conf_deposit.encrypted_balance = [0u8; 32]; // Zero balance initially
-        conf_deposit.last_update_slot = Clock::get()?.slot;
-        conf_deposit.is_active = true;
-        conf_deposit.bump = ctx.bumps.confidential_deposit;
+        require!(ctx.accounts.group_pool.is_active, ErrorCode::PoolNotActive);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(voting_slots > 0, ErrorCode::InvalidVotingPeriod);
+        require!(
+            quorum_bps > 0 && quorum_bps <= MAX_BASIS_POINTS,
+            ErrorCode::InvalidQuorum
+        );
+        require!(
+            ctx.accounts.member_position.contributed_amount > 0,
+            ErrorCode::NoVotingWeight
+        );
 
-        msg!("Confidential deposit initialized: {}", conf_deposit.owner);
-        msg!("Balance: [ENCRYPTED]");
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.pool = ctx.accounts.group_pool.key();
+        proposal.proposal_id = proposal_id;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.action = action;
+        proposal.token = token;
+        proposal.amount = amount;
+        proposal.recipient = if action == ProposalAction::Release {
+            recipient
+        } else {
+            Pubkey::default()
+        };
+        proposal.created_slot = Clock::get()?.slot;
+        proposal.deadline_slot = proposal.created_slot.saturating_add(voting_slots);
+        proposal.quorum_bps = quorum_bps;
+        proposal.total_weight = ctx.accounts.group_pool.total_locked;
+        proposal.yes_weight = 0;
+        proposal.no_weight = 0;
+        proposal.status = ProposalStatus::Voting;
+        proposal.bump = ctx.bumps.proposal;
+
+        msg!(
+            "Proposal {} opened on group {}: {:?} {}",
+            proposal_id,
+            ctx.accounts.group_pool.group_id,
+            action,
+            amount
+        );
         Ok(())
     }
 
-    /// Deposit with ENCRYPTED amount - PRIVACY ENABLED
-    ///
-    /// Flow:
-    /// 1. Client encrypts amount using Arcium SDK
-    /// 2. Transfers actual tokens (public chain operation)
-    /// 3. Queues MPC to update encrypted balance
-    /// 4. MPC callback updates encrypted balance
-    #[arcium_instruction(circuits::add_to_balance)]
-    pub fn confidential_deposit(
-        ctx: Context<ConfidentialDeposit>,
-        deposit_amount: u64,
-    ) -> Result<()> {
-        require!(deposit_amount > 0, ErrorCode::InvalidAmount);
-
-        // Transfer actual tokens
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.user_vault.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
-            deposit_amount,
-        )?;
+    /// Record one member's vote, weighted by their locked contribution. The
+    /// vote record PDA makes a second vote from the same member fail, and a
+    /// position topped up after the proposal opened can't vote, so the votes
+    /// cast never add up to more than the `total_weight` snapshot.
+    pub fn cast_vote(ctx: Context<CastVote>, approve: bool) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            Clock::get()?.slot < proposal.deadline_slot,
+            ErrorCode::VotingClosed
+        );
 
-        // Prepare encrypted input for MPC
-        let current_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_deposit.encrypted_balance
-        )?;
+        let weight = ctx.accounts.member_position.contributed_amount;
+        require!(weight > 0, ErrorCode::NoVotingWeight);
+        require!(
+            ctx.accounts.member_position.last_increase_slot < proposal.created_slot,
+            ErrorCode::VotingWeightChanged
+        );
 
-        let mpc_input = DepositInput {
-            current_balance,
-            deposit_amount,
-        };
+        if approve {
+            proposal.yes_weight += weight;
+        } else {
+            proposal.no_weight += weight;
+        }
 
-        // Queue MPC computation
-        ctx.accounts.mpc_request.invoke_mpc(
-            &mpc_input,
-            ctx.accounts.confidential_deposit.key(),
-        )?;
+        let vote = &mut ctx.accounts.vote_record;
+        vote.proposal = proposal.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.approve = approve;
+        vote.weight = weight;
+        vote.bump = ctx.bumps.vote_record;
 
-        msg!("Confidential deposit queued: user {}", ctx.accounts.user.key());
-        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        msg!(
+            "Vote on proposal {}: {} with weight {}",
+            proposal.proposal_id,
+            if approve { "yes" } else { "no" },
+            weight
+        );
         Ok(())
     }
 
-    /// MPC callback for confidential deposit
-    /// Called by Arcium network after encrypted computation
-    pub fn confidential_deposit_callback(
-        ctx: Context<ConfidentialDepositCallback>,
-        result: DepositResult,
-    ) -> Result<()> {
-        // Verify caller is Arcium MPC
+    /// Close voting once the deadline has passed. Anyone can call this.
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
         require!(
-            ctx.accounts.mpc_signer.key() == ARCIUM_MPC_AUTHORITY,
-            ErrorCode::InvalidMpcSigner
+            proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            Clock::get()?.slot >= proposal.deadline_slot,
+            ErrorCode::VotingStillOpen
         );
 
-        require!(result.success, ErrorCode::MpcComputationFailed);
+        proposal.status = proposal.outcome();
 
-        // Update encrypted balance
-        ctx.accounts.confidential_deposit.encrypted_balance =
-            serialize_encrypted_balance(result.new_balance);
-        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        emit!(ProposalFinalized {
+            proposal: proposal.key(),
+            status: proposal.status,
+            yes_weight: proposal.yes_weight,
+            no_weight: proposal.no_weight,
+        });
 
-        msg!("Confidential balance updated via MPC");
-        msg!("User: {}", ctx.accounts.confidential_deposit.owner);
+        msg!("Proposal {} finalized: {:?}", proposal.proposal_id, proposal.status);
         Ok(())
     }
 
-    /// Withdraw with ENCRYPTED amount - PRIVACY ENABLED
-    #[arcium_instruction(circuits::subtract_from_balance)]
-    pub fn confidential_withdraw(
-        ctx: Context<ConfidentialWithdraw>,
-        withdraw_amount: u64,
-        oracle_signature: [u8; 64],
-    ) -> Result<()> {
-        require!(withdraw_amount > 0, ErrorCode::InvalidAmount);
+    /// Pay out a passed `Release` proposal from the group vault to the
+    /// recipient's deposit. The vote replaces the oracle signature.
+    pub fn execute_release_proposal(ctx: Context<ExecuteReleaseProposal>) -> Result<()> {
+        let amount = ctx.accounts.proposal.amount;
+        let nav = ctx.accounts.group_pool.nav()?;
+        let entitlement = ctx
+            .accounts
+            .group_pool
+            .value_of(ctx.accounts.member_position.shares, nav);
+        require!(amount <= entitlement, ErrorCode::ExceedsEntitlement);
+        require!(
+            ctx.accounts.session.funds_unlocked(Clock::get()?.slot),
+            ErrorCode::SessionLocked
+        );
 
-        // Verify oracle signature
-        verify_oracle_signature(
-            &ctx.accounts.oracle_config,
-            format!(
-                "confidential_withdraw:{}:{}",
-                ctx.accounts.user.key(),
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
-        )?;
+        let seeds = &[
+            b"group_vault",
+            ctx.accounts.group_pool.group_id.as_bytes(),
+            ctx.accounts.group_pool.mint.as_ref(),
+            &[ctx.accounts.group_pool.vault_bump],
+        ];
 
-        // Prepare MPC input
-        let current_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_deposit.encrypted_balance
+        let received = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.group_vault,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.group_vault.to_account_info(),
+            &[&seeds[..]],
+            amount,
         )?;
 
-        let mpc_input = WithdrawInput {
-            current_balance,
-            withdraw_amount,
-        };
-
-        // Queue MPC computation (will verify balance)
-        ctx.accounts.mpc_request.invoke_mpc(
-            &mpc_input,
-            ctx.accounts.confidential_deposit.key(),
-        )?;
+        let shares = ctx.accounts.group_pool.shares_for_withdrawal(amount, nav);
+        ctx.accounts
+            .group_pool
+            .burn(&mut ctx.accounts.member_position, shares, amount);
+        ctx.accounts.user_deposit.deposited_amount += received;
+        ctx.accounts.proposal.status = ProposalStatus::Executed;
 
-        msg!("Confidential withdrawal queued: user {}", ctx.accounts.user.key());
-        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        msg!(
+            "Proposal {} executed: released {} from group {} to user {}",
+            ctx.accounts.proposal.proposal_id,
+            amount,
+            ctx.accounts.group_pool.group_id,
+            ctx.accounts.proposal.recipient
+        );
         Ok(())
     }
 
-    /// MPC callback for confidential withdrawal
-    pub fn confidential_withdraw_callback(
-        ctx: Context<ConfidentialWithdrawCallback>,
-        result: WithdrawResult,
+    // ========================================================================
+    // TRADING SESSIONS (PUBLIC POOLS)
+    // ========================================================================
+
+    /// Open a new trading session on a group pool (admin only)
+    ///
+    /// Members can release funds into the pool until `start_slot`; from
+    /// then until the session closes, nothing leaves the pool.
+    pub fn start_session(
+        ctx: Context<StartSession>,
+        start_slot: u64,
+        end_slot: u64,
     ) -> Result<()> {
-        // Verify MPC caller
+        let slot = Clock::get()?.slot;
         require!(
-            ctx.accounts.mpc_signer.key() == ARCIUM_MPC_AUTHORITY,
-            ErrorCode::InvalidMpcSigner
+            start_slot > slot && end_slot > start_slot,
+            ErrorCode::InvalidSessionWindow
         );
 
-        // Check if withdrawal approved by MPC (balance sufficient)
-        require!(result.success, ErrorCode::InsufficientBalance);
-
-        // Update encrypted balance
-        ctx.accounts.confidential_deposit.encrypted_balance =
-            serialize_encrypted_balance(result.new_balance);
-        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
-
-        // Calculate actual withdrawal amount (MPC verified this is valid)
-        // Note: In production, this should come from MPC result
-        let withdraw_amount = ctx.accounts.confidential_deposit.encrypted_balance[0] as u64;
-
-        // Transfer tokens
-        let seeds = &[
-            b"confidential_vault",
-            ctx.accounts.user.key().as_ref(),
-            &[ctx.accounts.confidential_deposit.bump],
-        ];
+        let session = &mut ctx.accounts.session;
+        let is_new = session.pool == Pubkey::default();
+        require!(
+            is_new || session.status == SessionStatus::Closed,
+            ErrorCode::SessionInProgress
+        );
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_vault.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.user_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
-            withdraw_amount,
-        )?;
+        session.pool = ctx.accounts.group_pool.key();
+        session.session_id = if is_new { 0 } else { session.session_id + 1 };
+        session.start_slot = start_slot;
+        session.end_slot = end_slot;
+        session.status = SessionStatus::Open;
+        session.bump = ctx.bumps.session;
 
-        msg!("Confidential withdrawal completed");
+        msg!(
+            "Session {} opened on group {}: slots {}..{}",
+            session.session_id,
+            ctx.accounts.group_pool.group_id,
+            start_slot,
+            end_slot
+        );
         Ok(())
     }
 
-    /// Create confidential group pool (dark pool)
-    pub fn create_confidential_group_pool(
-        ctx: Context<CreateConfidentialGroupPool>,
-        group_id: String,
-        group_name: String,
-    ) -> Result<()> {
-        let conf_pool = &mut ctx.accounts.confidential_pool;
-        conf_pool.admin = ctx.accounts.admin.key();
-        conf_pool.group_id = group_id.clone();
-        conf_pool.group_name = group_name;
-        conf_pool.encrypted_total = [0u8; 32];
-        conf_pool.participant_count = 0;
-        conf_pool.is_active = true;
-        conf_pool.meets_minimum_threshold = false;
-        conf_pool.bump = ctx.bumps.confidential_pool;
+    /// Move a session to its next state. Locking at `start_slot` and
+    /// settling at `end_slot` are open to anyone; the admin can make those
+    /// moves early and is the only one who can start trading or close.
+    pub fn advance_session(ctx: Context<AdvanceSession>) -> Result<()> {
+        let is_admin = ctx.accounts.caller.key() == ctx.accounts.group_pool.admin;
+        let session = &mut ctx.accounts.session;
+        let status = session.next_status(Clock::get()?.slot, is_admin)?;
+        // Claims pay out of the group vault alone, so everything bought has
+        // to be sold back first
+        if status == SessionStatus::Closed {
+            require!(
+                ctx.accounts.group_pool.open_positions.is_empty(),
+                ErrorCode::PoolHasOpenPositions
+            );
+        }
+        session.status = status;
+
+        emit!(SessionAdvanced {
+            session: session.key(),
+            session_id: session.session_id,
+            status: session.status,
+        });
 
-        msg!("Confidential pool created: {}", group_id);
-        msg!("Total: [ENCRYPTED - PRIVATE]");
+        msg!(
+            "Session {} on group {} is now {:?}",
+            session.session_id,
+            ctx.accounts.group_pool.group_id,
+            session.status
+        );
         Ok(())
     }
 
-    /// Release funds to dark pool with ENCRYPTED amount
-    #[arcium_instruction(circuits::confidential_transfer_to_pool)]
-    pub fn confidential_release_to_group(
-        ctx: Context<ConfidentialReleaseToGroup>,
-        transfer_amount: u64,
-        oracle_signature: [u8; 64],
-    ) -> Result<()> {
-        require!(transfer_amount > 0, ErrorCode::InvalidAmount);
-        require!(ctx.accounts.confidential_pool.is_active, ErrorCode::PoolNotActive);
+    /// Pay a member their pro-rata share of the group vault once the
+    /// session is closed. Anyone can crank this; the funds always go to the
+    /// member's own deposit.
+    pub fn claim_session_funds(ctx: Context<ClaimSessionFunds>) -> Result<()> {
+        // Claims pay out a finished session; while it is still open members
+        // leave through `release_funds_from_group`
+        require!(
+            ctx.accounts.session.status == SessionStatus::Closed,
+            ErrorCode::SessionNotClosed
+        );
 
-        // Verify oracle
-        verify_oracle_signature(
-            &ctx.accounts.oracle_config,
-            format!(
-                "confidential_release:{}:{}:{}",
-                ctx.accounts.user.key(),
-                ctx.accounts.confidential_pool.group_id,
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
-        )?;
+        let shares = ctx.accounts.member_position.shares;
+        require!(shares > 0, ErrorCode::NothingToClaim);
 
-        // Prepare MPC input
-        let user_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_deposit.encrypted_balance
-        )?;
-        let pool_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_pool.encrypted_total
-        )?;
+        // The vault may hold more or less than `total_locked` after trading,
+        // so shares are paid at the pool's current value
+        let nav = ctx.accounts.group_pool.nav()?;
+        let amount = ctx.accounts.group_pool.value_of(shares, nav);
 
-        let mpc_input = TransferInput {
-            user_balance,
-            pool_balance,
-            transfer_amount,
-        };
+        let seeds = &[
+            b"group_vault",
+            ctx.accounts.group_pool.group_id.as_bytes(),
+            ctx.accounts.group_pool.mint.as_ref(),
+            &[ctx.accounts.group_pool.vault_bump],
+        ];
 
-        // Queue MPC
-        ctx.accounts.mpc_request.invoke_mpc(
-            &mpc_input,
-            ctx.accounts.confidential_deposit.key(),
+        let received = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.group_vault,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.group_vault.to_account_info(),
+            &[&seeds[..]],
+            amount,
         )?;
 
+        ctx.accounts
+            .group_pool
+            .burn(&mut ctx.accounts.member_position, shares, amount);
+        ctx.accounts.user_deposit.deposited_amount += received;
+
         msg!(
-            "Confidential transfer to pool {} queued",
-            ctx.accounts.confidential_pool.group_id
+            "Claimed {} from group {} for user {}",
+            amount,
+            ctx.accounts.group_pool.group_id,
+            ctx.accounts.member_position.member
         );
-        msg!("Amount: [ENCRYPTED - PRIVATE]");
         Ok(())
     }
 
-    /// MPC callback for confidential transfer to pool
-    pub fn confidential_transfer_to_pool_callback(
-        ctx: Context<ConfidentialTransferCallback>,
-        result: TransferResult,
-    ) -> Result<()> {
-        // Verify MPC
+    /// Read a member's stake in a public pool. Nothing is written; clients
+    /// call this with `.view()` and read the returned `MemberStake`. Like an
+    /// exit, it fails while the pool holds bought tokens.
+    pub fn get_member_position(ctx: Context<GetMemberPosition>) -> Result<MemberStake> {
+        let pool = &ctx.accounts.group_pool;
+        let position = &ctx.accounts.member_position;
+        let nav = pool.nav()?;
+        Ok(MemberStake {
+            pool: position.pool,
+            member: position.member,
+            contributed_amount: position.contributed_amount,
+            shares: position.shares,
+            entitlement: pool.value_of(position.shares, nav),
+            pool_total_locked: pool.total_locked,
+            pool_total_shares: pool.total_shares,
+            pool_nav: nav,
+        })
+    }
+
+    // ========================================================================
+    // POOL SWAPS (PUBLIC POOLS)
+    // ========================================================================
+
+    /// Allow a DEX program for `execute_pool_swap` (authority only)
+    pub fn add_swap_program(ctx: Context<UpdateSwapAllowlist>, swap_program: Pubkey) -> Result<()> {
+        let allowlist = &mut ctx.accounts.swap_allowlist;
+        require!(
+            !allowlist.programs.contains(&swap_program),
+            ErrorCode::SwapProgramAlreadyAllowed
+        );
         require!(
-            ctx.accounts.mpc_signer.key() == ARCIUM_MPC_AUTHORITY,
-            ErrorCode::InvalidMpcSigner
+            allowlist.programs.len() < MAX_SWAP_PROGRAMS,
+            ErrorCode::TooManySwapPrograms
         );
 
-        require!(result.transfer_success, ErrorCode::TransferFailed);
+        allowlist.programs.push(swap_program);
+        allowlist.bump = ctx.bumps.swap_allowlist;
+        msg!("Swap program allowed: {}", swap_program);
+        Ok(())
+    }
 
-        // Update encrypted balances
-        ctx.accounts.confidential_deposit.encrypted_balance =
-            serialize_encrypted_balance(result.new_user_balance);
-        ctx.accounts.confidential_pool.encrypted_total =
-            serialize_encrypted_balance(result.new_pool_balance);
+    /// Remove a DEX program from the swap allowlist (authority only)
+    pub fn remove_swap_program(ctx: Context<UpdateSwapAllowlist>, swap_program: Pubkey) -> Result<()> {
+        let allowlist = &mut ctx.accounts.swap_allowlist;
+        let index = allowlist
+            .programs
+            .iter()
+            .position(|key| *key == swap_program)
+            .ok_or(ErrorCode::SwapProgramNotAllowed)?;
+
+        allowlist.programs.remove(index);
+        msg!("Swap program removed: {}", swap_program);
+        Ok(())
+    }
+
+    /// Trade pool funds for a passed `Buy` or `Sell` proposal (admin only)
+    ///
+    /// A `Buy` spends up to `amount` of the pool's mint for
+    /// `proposal.token`, a `Sell` the reverse. Bought tokens are held in a
+    /// per-mint `pool_token_vault` and listed in `open_positions` until sold
+    /// off. The swap program's accounts go in `remaining_accounts` with
+    /// `swap_data` as its instruction data; the group vault signs for both
+    /// vaults, and no other account it owns may be passed writable. Fails
+    /// unless at least `min_amount_out` lands in the output vault.
+    pub fn execute_pool_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecutePoolSwap<'info>>,
+        min_amount_out: u64,
+        swap_data: Vec<u8>,
+    ) -> Result<()> {
+        require!(min_amount_out > 0, ErrorCode::InvalidAmount);
+        let action = ctx.accounts.proposal.action;
+        require!(
+            ctx.accounts.session.accepts_swap(action, Clock::get()?.slot),
+            ErrorCode::SessionNotTrading
+        );
+        check_mint_extensions(&ctx.accounts.token_mint, true)?;
 
-        // Update public metadata
-        ctx.accounts.confidential_pool.participant_count += 1;
+        let is_buy = action == ProposalAction::Buy;
+        let (input_before, output_before) = if is_buy {
+            (ctx.accounts.group_vault.amount, ctx.accounts.token_vault.amount)
+        } else {
+            (ctx.accounts.token_vault.amount, ctx.accounts.group_vault.amount)
+        };
+
+        // The group vault owns both vaults, so it is the one signer the
+        // swap program gets. Its signature would reach any other token
+        // account it owns, so only the two vaults of this trade may be
+        // passed writable.
+        let group_vault = ctx.accounts.group_vault.key();
+        let token_vault = ctx.accounts.token_vault.key();
+        for account in ctx.remaining_accounts {
+            let declared = account.key() == group_vault || account.key() == token_vault;
+            if !account.is_writable || declared {
+                continue;
+            }
+            if let Ok(other) = InterfaceAccount::<TokenAccount>::try_from(account) {
+                require!(other.owner != group_vault, ErrorCode::SwapAccountNotAllowed);
+            }
+        }
+        let accounts = ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer || account.key() == group_vault,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let swap_ix = Instruction {
+            program_id: ctx.accounts.swap_program.key(),
+            accounts,
+            data: swap_data,
+        };
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.swap_program.to_account_info());
 
-        // Transfer actual tokens
         let seeds = &[
-            b"confidential_vault",
-            ctx.accounts.user.key().as_ref(),
-            &[ctx.accounts.confidential_deposit.bump],
+            b"group_vault",
+            ctx.accounts.group_pool.group_id.as_bytes(),
+            ctx.accounts.group_pool.mint.as_ref(),
+            &[ctx.accounts.group_pool.vault_bump],
         ];
+        invoke_signed(&swap_ix, &account_infos, &[&seeds[..]])?;
+
+        // Judge the swap by what the vaults hold, not by what the DEX reports,
+        // and make sure it didn't use the pool's signature to hand either
+        // vault to someone else
+        ctx.accounts.group_vault.reload()?;
+        ctx.accounts.token_vault.reload()?;
+        check_vault_unchanged(&ctx.accounts.group_vault, group_vault)?;
+        check_vault_unchanged(&ctx.accounts.token_vault, group_vault)?;
+        let (input_after, output_after) = if is_buy {
+            (ctx.accounts.group_vault.amount, ctx.accounts.token_vault.amount)
+        } else {
+            (ctx.accounts.token_vault.amount, ctx.accounts.group_vault.amount)
+        };
+        let amount_in = input_before.saturating_sub(input_after);
+        let amount_out = output_after.saturating_sub(output_before);
+        require!(
+            amount_in <= ctx.accounts.proposal.amount,
+            ErrorCode::SwapInputExceeded
+        );
+        require!(amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
+
+        // Only the program moves the NAV. A buy may also spend tokens sent
+        // straight to the vault, which were never counted.
+        let pool = &mut ctx.accounts.group_pool;
+        pool.net_asset_value = if is_buy {
+            pool.net_asset_value.saturating_sub(amount_in)
+        } else {
+            pool.net_asset_value + amount_out
+        };
 
-        // Note: transfer_amount should come from MPC in production
-        let transfer_amount = 100; // Placeholder
+        // Track which token vaults hold part of the pool's value
+        let token_mint = ctx.accounts.token_mint.key();
+        let positions = &mut ctx.accounts.group_pool.open_positions;
+        if is_buy && !positions.contains(&token_mint) {
+            require!(
+                positions.len() < MAX_POOL_POSITIONS,
+                ErrorCode::TooManyOpenPositions
+            );
+            positions.push(token_mint);
+        } else if !is_buy && ctx.accounts.token_vault.amount == 0 {
+            positions.retain(|mint| *mint != token_mint);
+        }
+
+        ctx.accounts.proposal.status = ProposalStatus::Executed;
+
+        emit!(PoolSwapExecuted {
+            pool: ctx.accounts.group_pool.key(),
+            proposal: ctx.accounts.proposal.key(),
+            swap_program: ctx.accounts.swap_program.key(),
+            action,
+            amount_in,
+            amount_out,
+        });
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_vault.to_account_info(),
-                    to: ctx.accounts.group_vault.to_account_info(),
-                    authority: ctx.accounts.user_vault.to_account_info(),
-                },
-                &[&seeds[..]],
-            ),
-            transfer_amount,
-        )?;
+        msg!(
+            "Proposal {} executed: {:?} {} of {} for {}",
+            ctx.accounts.proposal.proposal_id,
+            action,
+            amount_in,
+            ctx.accounts.token_mint.key(),
+            amount_out
+        );
+        Ok(())
+    }
 
-        msg!("Confidential transfer to pool completed");
-        msg!("Participants: {}", ctx.accounts.confidential_pool.participant_count);
+    /// Drop a bought token the pool can no longer sell from its open
+    /// positions, for a passed `WriteOff` proposal (admin only)
+    ///
+    /// The tokens stay in their `pool_token_vault` but count for nothing,
+    /// so members can exit and the session can close again. A later `Sell`
+    /// still works if the token turns out to be worth something.
+    pub fn write_off_position(ctx: Context<WriteOffPosition>) -> Result<()> {
+        let token = ctx.accounts.proposal.token;
+        let positions = &mut ctx.accounts.group_pool.open_positions;
+        let index = positions
+            .iter()
+            .position(|mint| *mint == token)
+            .ok_or(ErrorCode::PositionNotOpen)?;
+        positions.remove(index);
+
+        ctx.accounts.proposal.status = ProposalStatus::Executed;
+
+        msg!(
+            "Proposal {} executed: wrote off {} on group {}",
+            ctx.accounts.proposal.proposal_id,
+            token,
+            ctx.accounts.group_pool.group_id
+        );
         Ok(())
     }
 
-    /// Release funds from dark pool back to user with ENCRYPTED amount
-    #[arcium_instruction(circuits::confidential_transfer_from_pool)]
-    pub fn confidential_release_from_group(
-        ctx: Context<ConfidentialReleaseFromGroup>,
-        transfer_amount: u64,
-        oracle_signature: [u8; 64],
+    // ========================================================================
+    // COMPUTATION DEFINITIONS
+    // ========================================================================
+
+    /// Initialize and finalize the `add_to_balance` computation definition
+    pub fn init_add_to_balance_comp_def(ctx: Context<InitAddToBalanceCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::AddToBalance, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `subtract_from_balance` computation definition
+    pub fn init_subtract_from_balance_comp_def(ctx: Context<InitSubtractFromBalanceCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::SubtractFromBalance, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `confidential_transfer_to_pool` computation definition
+    pub fn init_confidential_transfer_to_pool_comp_def(ctx: Context<InitConfidentialTransferToPoolCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::ConfidentialTransferToPool, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `confidential_transfer_from_pool` computation definition
+    pub fn init_confidential_transfer_from_pool_comp_def(ctx: Context<InitConfidentialTransferFromPoolCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::ConfidentialTransferFromPool, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `verify_minimum_balance` computation definition
+    pub fn init_verify_minimum_balance_comp_def(ctx: Context<InitVerifyMinimumBalanceCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::VerifyMinimumBalance, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `compute_user_share` computation definition
+    pub fn init_compute_user_share_comp_def(ctx: Context<InitComputeUserShareCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::ComputeUserShare, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    /// Initialize and finalize the `compute_aggregate_stats` computation definition
+    pub fn init_compute_aggregate_stats_comp_def(
+        ctx: Context<InitComputeAggregateStatsCompDef>,
     ) -> Result<()> {
-        require!(transfer_amount > 0, ErrorCode::InvalidAmount);
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::ComputeAggregateStats, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
 
-        // Verify oracle
-        verify_oracle_signature(
-            &ctx.accounts.oracle_config,
-            format!(
-                "confidential_release_from:{}:{}:{}",
-                ctx.accounts.user.key(),
-                ctx.accounts.confidential_pool.group_id,
-                Clock::get()?.unix_timestamp / 300
-            )
-            .as_bytes(),
-            &oracle_signature,
-        )?;
+    /// Initialize and finalize the `add_weighted_vote` computation definition
+    pub fn init_add_weighted_vote_comp_def(ctx: Context<InitAddWeightedVoteCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::AddWeightedVote, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
 
-        // Prepare MPC input
-        let user_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_deposit.encrypted_balance
+    /// Initialize and finalize the `tally_votes` computation definition
+    pub fn init_tally_votes_comp_def(ctx: Context<InitTallyVotesCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        ctx.accounts
+            .comp_def_registry
+            .register(Circuit::TallyVotes, ctx.bumps.comp_def_registry);
+        Ok(())
+    }
+
+    // ========================================================================
+    // CONFIDENTIAL OPERATIONS (ARCIUM DARK POOL)
+    // ========================================================================
+
+    /// Initialize confidential user deposit account with encrypted balance
+    ///
+    /// `encryption_pubkey` is the owner's x25519 key; every balance the MPC
+    /// writes back is encrypted to it, so only the owner can decrypt.
+    pub fn initialize_confidential_deposit(
+        ctx: Context<InitializeConfidentialDeposit>,
+        encryption_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let conf_deposit = &mut ctx.accounts.confidential_deposit;
+        conf_deposit.owner = ctx.accounts.user.key();
+        conf_deposit.mint = ctx.accounts.mint.key();
+        conf_deposit.encryption_pubkey = encryption_pubkey;
+        conf_deposit.balance_nonce = 0;
+        conf_deposit.encrypted_balance = [0u8; 32]; // Zero balance initially
+        conf_deposit.balance_initialized = false;
+        conf_deposit.last_update_slot = Clock::get()?.slot;
+        conf_deposit.is_active = true;
+        conf_deposit.oracle_nonce = 0;
+        conf_deposit.pending_request = None;
+        conf_deposit.bump = ctx.bumps.confidential_deposit;
+        conf_deposit.vault_bump = ctx.bumps.user_vault;
+        // The encrypted ledger can't absorb transfer fees, so fee mints stay public-only
+        check_mint_extensions(&ctx.accounts.mint, false)?;
+
+        msg!("Confidential deposit initialized: {}", conf_deposit.owner);
+        msg!("Balance: [ENCRYPTED]");
+        Ok(())
+    }
+
+    /// Deposit with ENCRYPTED amount - PRIVACY ENABLED
+    ///
+    /// Flow:
+    /// 1. Transfers actual tokens into a per-request escrow
+    /// 2. Queues `add_to_balance` on the stored encrypted balance
+    /// 3. MPC callback writes back the re-encrypted balance and sweeps the
+    ///    escrow into the vault; if the request fails instead, the user
+    ///    takes the tokens back with `refund_confidential_deposit`
+    pub fn confidential_deposit(
+        ctx: Context<ConfidentialDeposit>,
+        computation_offset: u64,
+        deposit_amount: u64,
+    ) -> Result<()> {
+        require!(deposit_amount > 0, ErrorCode::InvalidAmount);
+
+        // Transfer actual tokens
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_token_account,
+            &mut ctx.accounts.deposit_escrow,
+            &ctx.accounts.mint,
+            ctx.accounts.user.to_account_info(),
+            &[],
+            deposit_amount,
         )?;
-        let pool_balance = deserialize_encrypted_balance(
-            &ctx.accounts.confidential_pool.encrypted_total
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_deposit.key(),
+            Pubkey::default(),
+            Circuit::AddToBalance,
+            ctx.bumps.mpc_request,
         )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Hold the balance until the callback or expiry, so a second
+        // computation can't start from the same ciphertext
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+
+        // Encrypted balance plus the deposit amount, which the token
+        // transfer above already made public
+        let args = ctx
+            .accounts
+            .confidential_deposit
+            .balance_args(ArgBuilder::new())
+            .plaintext_u64(deposit_amount)
+            .build();
+
+        let callback = AddToBalanceCallback::callback_ix(&deposit_callback_accounts(
+            ctx.accounts.mpc_request.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.deposit_escrow.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+        ));
 
-        let mpc_input = TransferInput {
-            user_balance,
-            pool_balance,
-            transfer_amount,
-        };
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
 
-        // Queue MPC
-        ctx.accounts.mpc_request.invoke_mpc(
-            &mpc_input,
-            ctx.accounts.confidential_pool.key(),
+        msg!("Confidential deposit queued: user {}", ctx.accounts.user.key());
+        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// `confidential_deposit` for native SOL: the lamports are wrapped into
+    /// a wSOL escrow, and the same `add_to_balance` callback sweeps them
+    /// into the user's wSOL vault
+    pub fn confidential_deposit_sol(
+        ctx: Context<ConfidentialDepositSol>,
+        computation_offset: u64,
+        lamports: u64,
+    ) -> Result<()> {
+        require!(lamports > 0, ErrorCode::InvalidAmount);
+
+        wrap_sol(
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.deposit_escrow.to_account_info(),
+            lamports,
         )?;
 
-        msg!(
-            "Confidential transfer from pool {} queued",
-            ctx.accounts.confidential_pool.group_id
-        );
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_deposit.key(),
+            Pubkey::default(),
+            Circuit::AddToBalance,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+
+        let args = ctx
+            .accounts
+            .confidential_deposit
+            .balance_args(ArgBuilder::new())
+            .plaintext_u64(lamports)
+            .build();
+
+        let callback = AddToBalanceCallback::callback_ix(&deposit_callback_accounts(
+            ctx.accounts.mpc_request.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.deposit_escrow.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+        ));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!("Confidential SOL deposit queued: user {}", ctx.accounts.user.key());
         msg!("Amount: [ENCRYPTED - PRIVATE]");
         Ok(())
     }
 
-    /// MPC callback for confidential transfer from pool
-    pub fn confidential_transfer_from_pool_callback(
-        ctx: Context<ConfidentialTransferFromPoolCallback>,
-        result: TransferResult,
+    /// MPC callback for confidential deposit
+    /// Called by Arcium network after encrypted computation
+    #[arcium_callback(encrypted_ix = "add_to_balance")]
+    pub fn add_to_balance_callback(
+        ctx: Context<AddToBalanceCallback>,
+        output: ComputationOutputs<AddToBalanceOutput>,
     ) -> Result<()> {
-        // Verify MPC
-        require!(
-            ctx.accounts.mpc_signer.key() == ARCIUM_MPC_AUTHORITY,
-            ErrorCode::InvalidMpcSigner
-        );
-
-        require!(result.transfer_success, ErrorCode::TransferFailed);
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.unlock(request);
+
+        let new_balance = match output {
+            ComputationOutputs::Success(AddToBalanceOutput { field_0 }) => field_0,
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.user.to_account_info(),
+                )
+            }
+        };
 
-        // Update encrypted balances
-        ctx.accounts.confidential_deposit.encrypted_balance =
-            serialize_encrypted_balance(result.new_user_balance);
-        ctx.accounts.confidential_pool.encrypted_total =
-            serialize_encrypted_balance(result.new_pool_balance);
+        // Update encrypted balance
+        ctx.accounts.confidential_deposit.store_balance(&new_balance)?;
+        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+
+        // The ledger now credits the deposit, so the escrowed tokens can join the vault
+        drain_deposit_escrow(
+            &ctx.accounts.token_program,
+            &ctx.accounts.deposit_escrow,
+            ctx.bumps.deposit_escrow,
+            request,
+            &ctx.accounts.mint,
+            &mut ctx.accounts.user_vault,
+            ctx.accounts.user.to_account_info(),
+        )?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.user.to_account_info())?;
 
-        // Transfer tokens from pool to user
-        let seeds = &[
-            b"confidential_group_vault",
-            ctx.accounts.confidential_pool.group_id.as_bytes(),
-            &[ctx.accounts.confidential_pool.bump],
-        ];
+        emit!(ConfidentialBalanceUpdated {
+            owner: ctx.accounts.confidential_deposit.owner,
+            slot: ctx.accounts.confidential_deposit.last_update_slot,
+        });
 
-        let transfer_amount = 100; // Should come from MPC
+        msg!("Confidential balance updated via MPC");
+        msg!("User: {}", ctx.accounts.confidential_deposit.owner);
+        Ok(())
+    }
 
-        token::transfer(
-            CpiContext::new_with_signer(
+    /// Return escrowed deposit tokens after the deposit's MPC request failed
+    /// or expired, so they never sit in the vault without a ledger credit
+    ///
+    /// A wSOL escrow is unwrapped straight to the user's wallet, so
+    /// `user_token_account` is only needed for SPL deposits.
+    pub fn refund_confidential_deposit(ctx: Context<RefundConfidentialDeposit>) -> Result<()> {
+        let refunded = if ctx.accounts.deposit_escrow.is_native() {
+            let amount = ctx.accounts.deposit_escrow.amount;
+            let request = ctx.accounts.mpc_request.key();
+            let seeds: &[&[u8]] = &[b"deposit_escrow", request.as_ref(), &[ctx.bumps.deposit_escrow]];
+            token_interface::close_account(CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.group_vault.to_account_info(),
-                    to: ctx.accounts.user_vault.to_account_info(),
-                    authority: ctx.accounts.group_vault.to_account_info(),
+                CloseAccount {
+                    account: ctx.accounts.deposit_escrow.to_account_info(),
+                    destination: ctx.accounts.user.to_account_info(),
+                    authority: ctx.accounts.deposit_escrow.to_account_info(),
                 },
-                &[&seeds[..]],
-            ),
-            transfer_amount,
-        )?;
+                &[seeds],
+            ))?;
+            amount
+        } else {
+            let user_token_account = ctx
+                .accounts
+                .user_token_account
+                .as_mut()
+                .ok_or(ErrorCode::TokenAccountRequired)?;
+            drain_deposit_escrow(
+                &ctx.accounts.token_program,
+                &ctx.accounts.deposit_escrow,
+                ctx.bumps.deposit_escrow,
+                ctx.accounts.mpc_request.key(),
+                &ctx.accounts.mint,
+                user_token_account,
+                ctx.accounts.user.to_account_info(),
+            )?
+        };
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.user.to_account_info())?;
 
-        msg!("Confidential transfer from pool completed");
+        msg!(
+            "Confidential deposit refunded: user {}, amount {}",
+            ctx.accounts.user.key(),
+            refunded
+        );
         Ok(())
     }
-}
 
-// ========================================================================
-// HELPER FUNCTIONS
-// ========================================================================
+    /// Withdraw with ENCRYPTED amount - PRIVACY ENABLED
+    pub fn confidential_withdraw(
+        ctx: Context<ConfidentialWithdraw>,
+        computation_offset: u64,
+        withdraw_amount: EncryptedAmount,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Result<()> {
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::ConfidentialWithdraw,
+            ctx.accounts.user.key(),
+            Pubkey::default(),
+            ctx.accounts.user_vault.mint,
+            OracleAmount::Commitment(withdraw_amount.commitment()),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
+            &ctx.accounts.oracle_config,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.confidential_deposit.oracle_nonce,
+        )?;
 
-/// Verify oracle signature (placeholder - implement with ed25519 in production)
-fn verify_oracle_signature(
-    oracle_config: &Account<OracleConfig>,
-    message: &[u8],
-    _signature: &[u8; 64],
-) -> Result<()> {
-    msg!("Oracle verification: {:?}", message);
-    msg!("Oracle pubkey: {}", oracle_config.oracle_pubkey);
-    // TODO: Implement proper Ed25519 signature verification
-    Ok(())
-}
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_deposit.key(),
+            Pubkey::default(),
+            Circuit::SubtractFromBalance,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Hold the balance until the callback or expiry, so a second
+        // computation can't start from the same ciphertext
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+
+        // Prepare MPC input (will verify balance)
+        let args = withdraw_amount
+            .args(ctx.accounts.confidential_deposit.balance_args(ArgBuilder::new()))
+            .build();
+
+        let callback = SubtractFromBalanceCallback::callback_ix(&[
+            CallbackAccount {
+                pubkey: ctx.accounts.mpc_request.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.confidential_deposit.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.user_vault.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.user_token_account.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.user.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.mint.key(),
+                is_writable: false,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.token_program.key(),
+                is_writable: false,
+            },
+        ]);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
 
-/// Deserialize encrypted balance (placeholder)
-fn deserialize_encrypted_balance(encrypted: &[u8; 32]) -> Result<u64> {
-    // In production, use Arcium's deserialization
-    // This is a placeholder that treats first 8 bytes as u64
-    Ok(u64::from_le_bytes([
-        encrypted[0],
-        encrypted[1],
-        encrypted[2],
-        encrypted[3],
-        encrypted[4],
-        encrypted[5],
-        encrypted[6],
-        encrypted[7],
-    ]))
-}
+        msg!("Confidential withdrawal queued: user {}", ctx.accounts.user.key());
+        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
 
-/// Serialize encrypted balance (placeholder)
-fn serialize_encrypted_balance(balance: u64) -> [u8; 32] {
-    let mut result = [0u8; 32];
-    let bytes = balance.to_le_bytes();
-    result[0..8].copy_from_slice(&bytes);
-    result
-}
+    /// MPC callback for confidential withdrawal
+    #[arcium_callback(encrypted_ix = "subtract_from_balance")]
+    pub fn subtract_from_balance_callback(
+        ctx: Context<SubtractFromBalanceCallback>,
+        output: ComputationOutputs<SubtractFromBalanceOutput>,
+    ) -> Result<()> {
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.unlock(request);
+
+        let (new_balance, success, withdraw_amount) = match output {
+            ComputationOutputs::Success(SubtractFromBalanceOutput {
+                field_0:
+                    SubtractFromBalanceOutputStruct0 {
+                        field_0: new_balance,
+                        field_1: success,
+                        field_2: withdraw_amount,
+                    },
+            }) => (new_balance, success, withdraw_amount),
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.user.to_account_info(),
+                )
+            }
+        };
 
-// ========================================================================
-// ACCOUNT STRUCTURES
-// ========================================================================
+        // Withdrawal not approved by MPC (balance insufficient)
+        if !success {
+            return fail_mpc_request(
+                &mut ctx.accounts.mpc_request,
+                ctx.accounts.user.to_account_info(),
+            );
+        }
 
-#[account]
-pub struct OracleConfig {
-    pub authority: Pubkey,
-    pub oracle_pubkey: Pubkey,
-    pub bump: u8,
-}
+        // Update encrypted balance
+        ctx.accounts.confidential_deposit.store_balance(&new_balance)?;
+        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
 
-#[account]
-pub struct UserDeposit {
-    pub owner: Pubkey,
-    pub deposited_amount: u64,
-    pub bump: u8,
-}
+        emit!(ConfidentialBalanceUpdated {
+            owner: ctx.accounts.confidential_deposit.owner,
+            slot: ctx.accounts.confidential_deposit.last_update_slot,
+        });
 
-#[account]
-pub struct GroupPool {
-    pub admin: Pubkey,
-    pub group_id: String,
-    pub group_name: String,
-    pub total_locked: u64,
-    pub is_active: bool,
-    pub bump: u8,
-}
+        // Transfer exactly the amount the MPC debited from the encrypted balance
+        let seeds = &[
+            b"confidential_vault",
+            ctx.accounts.confidential_deposit.owner.as_ref(),
+            ctx.accounts.confidential_deposit.mint.as_ref(),
+            &[ctx.accounts.confidential_deposit.vault_bump],
+        ];
 
-// CONFIDENTIAL STRUCTURES
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &mut ctx.accounts.user_token_account,
+            &ctx.accounts.mint,
+            ctx.accounts.user_vault.to_account_info(),
+            &[&seeds[..]],
+            withdraw_amount,
+        )?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.user.to_account_info())?;
 
-#[account]
-pub struct ConfidentialUserDeposit {
-    pub owner: Pubkey,
-    pub encrypted_balance: [u8; 32], // ENCRYPTED - private
-    pub last_update_slot: u64,
-    pub is_active: bool,
-    pub bump: u8,
-}
+        msg!("Confidential withdrawal completed");
+        Ok(())
+    }
 
-#[account]
-pub struct ConfidentialGroupPool {
-    pub admin: Pubkey,
+    /// Create confidential group pool (dark pool)
+    pub fn create_confidential_group_pool(
+        ctx: Context<CreateConfidentialGroupPool>,
+        group_id: String,
+        group_name: String,
+    ) -> Result<()> {
+        let conf_pool = &mut ctx.accounts.confidential_pool;
+        conf_pool.admin = ctx.accounts.admin.key();
+        conf_pool.mint = ctx.accounts.mint.key();
+        conf_pool.group_id = group_id.clone();
+        conf_pool.group_name = group_name;
+        conf_pool.total_nonce = 0;
+        conf_pool.encrypted_total = [0u8; 32];
+        conf_pool.total_initialized = false;
+        conf_pool.participants_nonce = 0;
+        conf_pool.encrypted_participants = [0u8; 32];
+        conf_pool.participant_count = 0;
+        conf_pool.is_active = true;
+        conf_pool.meets_minimum_threshold = false;
+        conf_pool.pending_request = None;
+        conf_pool.bump = ctx.bumps.confidential_pool;
+        conf_pool.vault_bump = ctx.bumps.group_vault;
+        check_mint_extensions(&ctx.accounts.mint, false)?;
+
+        msg!("Confidential pool created: {}", group_id);
+        msg!("Total: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// Release funds to dark pool with ENCRYPTED amount
+    pub fn confidential_release_to_group(
+        ctx: Context<ConfidentialReleaseToGroup>,
+        computation_offset: u64,
+        transfer_amount: EncryptedAmount,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Result<()> {
+        require!(ctx.accounts.confidential_pool.is_active, ErrorCode::PoolNotActive);
+
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::ConfidentialReleaseToGroup,
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.user_vault.mint,
+            OracleAmount::Commitment(transfer_amount.commitment()),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
+            &ctx.accounts.oracle_config,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.confidential_deposit.oracle_nonce,
+        )?;
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.confidential_pool.key(),
+            Circuit::ConfidentialTransferToPool,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Hold the balance until the callback or expiry, so a second
+        // computation can't start from the same ciphertext
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+        ctx.accounts.confidential_pool.lock(request)?;
+        ctx.accounts.confidential_member.lock(request)?;
+
+        let member = &mut ctx.accounts.confidential_member;
+        member.pool = ctx.accounts.confidential_pool.key();
+        member.member = ctx.accounts.user.key();
+        member.bump = ctx.bumps.confidential_member;
+
+        // Prepare MPC input
+        let args = ctx.accounts.confidential_deposit.balance_args(ArgBuilder::new());
+        let args = ctx.accounts.confidential_pool.total_args(args);
+        let args = ctx.accounts.confidential_member.contribution_args(args);
+        let args = transfer_amount.args(args).build();
+
+        let callback = ConfidentialTransferToPoolCallback::callback_ix(&pool_transfer_callback_accounts(
+            ctx.accounts.mpc_request.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.group_vault.key(),
+            ctx.accounts.confidential_member.key(),
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+        ));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Confidential transfer to pool {} queued",
+            ctx.accounts.confidential_pool.group_id
+        );
+        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// MPC callback for confidential transfer to pool
+    #[arcium_callback(encrypted_ix = "confidential_transfer_to_pool")]
+    pub fn confidential_transfer_to_pool_callback(
+        ctx: Context<ConfidentialTransferToPoolCallback>,
+        output: ComputationOutputs<ConfidentialTransferToPoolOutput>,
+    ) -> Result<()> {
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.unlock(request);
+        ctx.accounts.confidential_pool.unlock(request);
+        ctx.accounts.confidential_member.unlock(request);
+
+        let (
+            new_user_balance,
+            new_pool_total,
+            transfer_success,
+            transfer_amount,
+            new_contribution,
+            new_participants,
+        ) = match output {
+            ComputationOutputs::Success(ConfidentialTransferToPoolOutput {
+                field_0:
+                    ConfidentialTransferToPoolOutputStruct0 {
+                        field_0: new_user_balance,
+                        field_1: new_pool_total,
+                        field_2: transfer_success,
+                        field_3: transfer_amount,
+                        field_4: new_contribution,
+                        field_5: new_participants,
+                    },
+            }) => (
+                new_user_balance,
+                new_pool_total,
+                transfer_success,
+                transfer_amount,
+                new_contribution,
+                new_participants,
+            ),
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.user.to_account_info(),
+                )
+            }
+        };
+
+        if !transfer_success {
+            return fail_mpc_request(
+                &mut ctx.accounts.mpc_request,
+                ctx.accounts.user.to_account_info(),
+            );
+        }
+
+        // Update encrypted balances
+        ctx.accounts.confidential_deposit.store_balance(&new_user_balance)?;
+        ctx.accounts
+            .confidential_pool
+            .store_total(&new_pool_total, &new_participants);
+        ctx.accounts
+            .confidential_member
+            .store_contribution(&new_contribution);
+        ctx.accounts.confidential_member.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+
+        emit!(ConfidentialBalanceUpdated {
+            owner: ctx.accounts.confidential_deposit.owner,
+            slot: ctx.accounts.confidential_deposit.last_update_slot,
+        });
+
+        // Transfer exactly the amount the MPC moved between the encrypted ledgers
+        let seeds = &[
+            b"confidential_vault",
+            ctx.accounts.confidential_deposit.owner.as_ref(),
+            ctx.accounts.confidential_deposit.mint.as_ref(),
+            &[ctx.accounts.confidential_deposit.vault_bump],
+        ];
+
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &mut ctx.accounts.group_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.user_vault.to_account_info(),
+            &[&seeds[..]],
+            transfer_amount,
+        )?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.user.to_account_info())?;
+
+        msg!("Confidential transfer to pool completed");
+        Ok(())
+    }
+
+    /// Release funds from dark pool back to user with ENCRYPTED amount
+    pub fn confidential_release_from_group(
+        ctx: Context<ConfidentialReleaseFromGroup>,
+        computation_offset: u64,
+        transfer_amount: EncryptedAmount,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Result<()> {
+        // Once closed, members leave through settlement so payouts stay pro rata
+        require!(ctx.accounts.confidential_pool.is_active, ErrorCode::PoolNotActive);
+
+        // Verify oracle authorization
+        let authorization = OracleAuthorization::new(
+            &ctx.accounts.oracle_config,
+            OracleAction::ConfidentialReleaseFromGroup,
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.group_vault.mint,
+            OracleAmount::Commitment(transfer_amount.commitment()),
+            nonce,
+            expiry_slot,
+        );
+        verify_oracle_authorization(
+            &ctx.accounts.oracle_config,
+            &ctx.accounts.instructions_sysvar,
+            &authorization,
+            &mut ctx.accounts.confidential_deposit.oracle_nonce,
+        )?;
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.user.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.confidential_pool.key(),
+            Circuit::ConfidentialTransferFromPool,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Hold the balance until the callback or expiry, so a second
+        // computation can't start from the same ciphertext
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+        ctx.accounts.confidential_pool.lock(request)?;
+        ctx.accounts.confidential_member.lock(request)?;
+
+        // Prepare MPC input
+        let args = ctx.accounts.confidential_deposit.balance_args(ArgBuilder::new());
+        let args = ctx.accounts.confidential_pool.total_args(args);
+        let args = ctx.accounts.confidential_member.contribution_args(args);
+        let args = transfer_amount.args(args).build();
+
+        let callback = ConfidentialTransferFromPoolCallback::callback_ix(&pool_transfer_callback_accounts(
+            ctx.accounts.mpc_request.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.group_vault.key(),
+            ctx.accounts.confidential_member.key(),
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+        ));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Confidential transfer from pool {} queued",
+            ctx.accounts.confidential_pool.group_id
+        );
+        msg!("Amount: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// MPC callback for confidential transfer from pool
+    #[arcium_callback(encrypted_ix = "confidential_transfer_from_pool")]
+    pub fn confidential_transfer_from_pool_callback(
+        ctx: Context<ConfidentialTransferFromPoolCallback>,
+        output: ComputationOutputs<ConfidentialTransferFromPoolOutput>,
+    ) -> Result<()> {
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.unlock(request);
+        ctx.accounts.confidential_pool.unlock(request);
+        ctx.accounts.confidential_member.unlock(request);
+
+        let (
+            new_user_balance,
+            new_pool_total,
+            transfer_success,
+            transfer_amount,
+            new_contribution,
+            new_participants,
+        ) = match output {
+            ComputationOutputs::Success(ConfidentialTransferFromPoolOutput {
+                field_0:
+                    ConfidentialTransferFromPoolOutputStruct0 {
+                        field_0: new_user_balance,
+                        field_1: new_pool_total,
+                        field_2: transfer_success,
+                        field_3: transfer_amount,
+                        field_4: new_contribution,
+                        field_5: new_participants,
+                    },
+            }) => (
+                new_user_balance,
+                new_pool_total,
+                transfer_success,
+                transfer_amount,
+                new_contribution,
+                new_participants,
+            ),
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.user.to_account_info(),
+                )
+            }
+        };
+
+        if !transfer_success {
+            return fail_mpc_request(
+                &mut ctx.accounts.mpc_request,
+                ctx.accounts.user.to_account_info(),
+            );
+        }
+
+        // Update encrypted balances
+        ctx.accounts.confidential_deposit.store_balance(&new_user_balance)?;
+        ctx.accounts
+            .confidential_pool
+            .store_total(&new_pool_total, &new_participants);
+        ctx.accounts
+            .confidential_member
+            .store_contribution(&new_contribution);
+        ctx.accounts.confidential_member.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+
+        emit!(ConfidentialBalanceUpdated {
+            owner: ctx.accounts.confidential_deposit.owner,
+            slot: ctx.accounts.confidential_deposit.last_update_slot,
+        });
+
+        // Transfer tokens from pool to user, exactly as much as the MPC moved
+        let seeds = &[
+            b"confidential_group_vault",
+            ctx.accounts.confidential_pool.group_id.as_bytes(),
+            ctx.accounts.confidential_pool.mint.as_ref(),
+            &[ctx.accounts.confidential_pool.vault_bump],
+        ];
+
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.group_vault,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.group_vault.to_account_info(),
+            &[&seeds[..]],
+            transfer_amount,
+        )?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.user.to_account_info())?;
+
+        msg!("Confidential transfer from pool completed");
+        Ok(())
+    }
+
+    /// Recompute dark pool health without revealing its total (admin only)
+    ///
+    /// Sets `meets_minimum_threshold` and `participant_count` once the
+    /// callback lands: the pool needs `MIN_POOL_PARTICIPANTS` members and at
+    /// least `minimum_total` locked. Both are counted inside the MPC.
+    pub fn compute_pool_stats(
+        ctx: Context<ComputePoolStats>,
+        computation_offset: u64,
+        minimum_total: u64,
+    ) -> Result<()> {
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.admin.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.confidential_pool.key(),
+            Circuit::ComputeAggregateStats,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Hold the pool state until the callback or expiry, so the stats
+        // describe a total no transfer is midway through changing
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_pool.lock(request)?;
+
+        let args = ctx
+            .accounts
+            .confidential_pool
+            .total_args(ArgBuilder::new())
+            .plaintext_u32(MIN_POOL_PARTICIPANTS)
+            .plaintext_u64(minimum_total)
+            .build();
+
+        let callback = ComputeAggregateStatsCallback::callback_ix(&[
+            CallbackAccount {
+                pubkey: ctx.accounts.mpc_request.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.confidential_pool.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.admin.key(),
+                is_writable: true,
+            },
+        ]);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Pool stats queued: {}",
+            ctx.accounts.confidential_pool.group_id
+        );
+        Ok(())
+    }
+
+    /// MPC callback for pool stats
+    #[arcium_callback(encrypted_ix = "compute_aggregate_stats")]
+    pub fn compute_aggregate_stats_callback(
+        ctx: Context<ComputeAggregateStatsCallback>,
+        output: ComputationOutputs<ComputeAggregateStatsOutput>,
+    ) -> Result<()> {
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_pool.unlock(request);
+
+        let (participant_count, has_minimum_participants, pool_is_healthy) = match output {
+            ComputationOutputs::Success(ComputeAggregateStatsOutput {
+                field_0:
+                    ComputeAggregateStatsOutputStruct0 {
+                        field_0: participant_count,
+                        field_1: has_minimum_participants,
+                        field_2: pool_is_healthy,
+                    },
+            }) => (participant_count, has_minimum_participants, pool_is_healthy),
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.admin.to_account_info(),
+                )
+            }
+        };
+
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.admin.to_account_info())?;
+
+        let pool = &mut ctx.accounts.confidential_pool;
+        pool.participant_count = participant_count;
+        pool.meets_minimum_threshold = has_minimum_participants && pool_is_healthy;
+
+        emit!(PoolStatsUpdated {
+            pool: pool.key(),
+            participant_count,
+            has_minimum_participants,
+            pool_is_healthy,
+        });
+
+        msg!("Pool stats updated: {}", pool.group_id);
+        msg!("Meets minimum threshold: {}", pool.meets_minimum_threshold);
+        Ok(())
+    }
+
+    /// End the dark pool's session (admin only). No more releases in or
+    /// out; members leave through `settle_confidential_member`.
+    pub fn close_confidential_pool(ctx: Context<CloseConfidentialPool>) -> Result<()> {
+        let pool = &mut ctx.accounts.confidential_pool;
+        require!(pool.is_active, ErrorCode::PoolNotActive);
+        pool.is_active = false;
+
+        msg!("Confidential pool closed: {}", pool.group_id);
+        Ok(())
+    }
+
+    /// Pay a member their pro-rata share of a closed dark pool
+    ///
+    /// Permissionless: anyone can crank a member's settlement, since the
+    /// payout only ever goes to the member's own confidential deposit.
+    pub fn settle_confidential_member(
+        ctx: Context<SettleConfidentialMember>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.confidential_pool.is_active, ErrorCode::PoolStillActive);
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.payer.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.confidential_pool.key(),
+            Circuit::ComputeUserShare,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.lock(request)?;
+        ctx.accounts.confidential_pool.lock(request)?;
+        ctx.accounts.confidential_member.lock(request)?;
+
+        // The vault can't move while the pool is locked, so the share is
+        // computed against exactly what the callback will pay out of
+        let args = ctx.accounts.confidential_deposit.balance_args(ArgBuilder::new());
+        let args = ctx.accounts.confidential_pool.total_args(args);
+        let args = ctx.accounts.confidential_member.contribution_args(args);
+        let args = args.plaintext_u64(ctx.accounts.group_vault.amount).build();
+
+        let callback = ComputeUserShareCallback::callback_ix(&pool_transfer_callback_accounts(
+            ctx.accounts.mpc_request.key(),
+            ctx.accounts.confidential_deposit.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.accounts.confidential_pool.key(),
+            ctx.accounts.group_vault.key(),
+            ctx.accounts.confidential_member.key(),
+            ctx.accounts.payer.key(),
+            ctx.accounts.mint.key(),
+            ctx.accounts.token_program.key(),
+        ));
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Settlement queued for pool {}",
+            ctx.accounts.confidential_pool.group_id
+        );
+        msg!("Contribution: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// MPC callback for a member settlement
+    #[arcium_callback(encrypted_ix = "compute_user_share")]
+    pub fn compute_user_share_callback(
+        ctx: Context<ComputeUserShareCallback>,
+        output: ComputationOutputs<ComputeUserShareOutput>,
+    ) -> Result<()> {
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.confidential_deposit.unlock(request);
+        ctx.accounts.confidential_pool.unlock(request);
+        ctx.accounts.confidential_member.unlock(request);
+
+        let (new_user_balance, new_pool_total, new_contribution, share, new_participants) =
+            match output {
+                ComputationOutputs::Success(ComputeUserShareOutput {
+                    field_0:
+                        ComputeUserShareOutputStruct0 {
+                            field_0: new_user_balance,
+                            field_1: new_pool_total,
+                            field_2: new_contribution,
+                            field_3: share,
+                            field_4: new_participants,
+                        },
+                }) => (
+                    new_user_balance,
+                    new_pool_total,
+                    new_contribution,
+                    share,
+                    new_participants,
+                ),
+                _ => {
+                    return fail_mpc_request(
+                        &mut ctx.accounts.mpc_request,
+                        ctx.accounts.requester.to_account_info(),
+                    )
+                }
+            };
+
+        ctx.accounts.confidential_deposit.store_balance(&new_user_balance)?;
+        ctx.accounts
+            .confidential_pool
+            .store_total(&new_pool_total, &new_participants);
+        ctx.accounts
+            .confidential_member
+            .store_contribution(&new_contribution);
+        ctx.accounts.confidential_member.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.confidential_deposit.last_update_slot = Clock::get()?.slot;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+
+        emit!(ConfidentialBalanceUpdated {
+            owner: ctx.accounts.confidential_deposit.owner,
+            slot: ctx.accounts.confidential_deposit.last_update_slot,
+        });
+
+        emit!(ConfidentialMemberSettled {
+            pool: ctx.accounts.confidential_pool.key(),
+            member: ctx.accounts.confidential_deposit.owner,
+            amount: share,
+        });
+
+        let seeds = &[
+            b"confidential_group_vault",
+            ctx.accounts.confidential_pool.group_id.as_bytes(),
+            ctx.accounts.confidential_pool.mint.as_ref(),
+            &[ctx.accounts.confidential_pool.vault_bump],
+        ];
+
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.group_vault,
+            &mut ctx.accounts.user_vault,
+            &ctx.accounts.mint,
+            ctx.accounts.group_vault.to_account_info(),
+            &[&seeds[..]],
+            share,
+        )?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.requester.to_account_info())?;
+
+        msg!("Member settled: {} tokens", share);
+        Ok(())
+    }
+
+    // ========================================================================
+    // CONFIDENTIAL VOTING (DARK POOLS)
+    // ========================================================================
+
+    /// Open a proposal on a dark pool. Only members who have moved funds
+    /// into the pool can propose.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_confidential_proposal(
+        ctx: Context<CreateConfidentialProposal>,
+        proposal_id: u64,
+        action: ProposalAction,
+        token: Pubkey,
+        amount: u64,
+        voting_slots: u64,
+        quorum_bps: u16,
+    ) -> Result<()> {
+        require!(ctx.accounts.confidential_pool.is_active, ErrorCode::PoolNotActive);
+        require!(voting_slots > 0, ErrorCode::InvalidVotingPeriod);
+        require!(
+            quorum_bps > 0 && quorum_bps <= MAX_BASIS_POINTS,
+            ErrorCode::InvalidQuorum
+        );
+        require!(
+            ctx.accounts.confidential_member.contribution_initialized,
+            ErrorCode::NoVotingWeight
+        );
+        // The snapshot below must not miss a transfer still in flight
+        require!(
+            ctx.accounts.confidential_pool.pending_request.is_none(),
+            ErrorCode::OperationPending
+        );
+
+        let pool = &ctx.accounts.confidential_pool;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.pool = pool.key();
+        proposal.proposal_id = proposal_id;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.action = action;
+        proposal.token = token;
+        proposal.amount = amount;
+        proposal.created_slot = Clock::get()?.slot;
+        proposal.deadline_slot = proposal.created_slot.saturating_add(voting_slots);
+        proposal.quorum_bps = quorum_bps;
+        // Turnout is measured against the pool as it stood when voting opened
+        proposal.total_nonce = pool.total_nonce;
+        proposal.encrypted_total = pool.encrypted_total;
+        proposal.total_initialized = pool.total_initialized;
+        proposal.tally_nonce = 0;
+        proposal.encrypted_tally = [[0u8; 32]; 2];
+        proposal.tally_initialized = false;
+        proposal.vote_count = 0;
+        proposal.turnout_bps = 0;
+        proposal.status = ProposalStatus::Voting;
+        proposal.pending_request = None;
+        proposal.bump = ctx.bumps.proposal;
+
+        msg!(
+            "Confidential proposal {} opened on pool {}: {:?}",
+            proposal_id,
+            pool.group_id,
+            action
+        );
+        Ok(())
+    }
+
+    /// Cast an ENCRYPTED vote, weighted in the MPC by the member's encrypted
+    /// pool contribution
+    ///
+    /// The vote record is created up front so a member can't queue two
+    /// votes; if the computation fails, the callback closes it again so the
+    /// member can retry. Only contributions that haven't moved since the
+    /// proposal opened can vote, so no funds are counted twice.
+    pub fn cast_confidential_vote(
+        ctx: Context<CastConfidentialVote>,
+        computation_offset: u64,
+        vote: EncryptedVote,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            Clock::get()?.slot < ctx.accounts.proposal.deadline_slot,
+            ErrorCode::VotingClosed
+        );
+        require!(
+            ctx.accounts.confidential_member.contribution_initialized,
+            ErrorCode::NoVotingWeight
+        );
+        require!(
+            ctx.accounts.confidential_member.last_update_slot < ctx.accounts.proposal.created_slot,
+            ErrorCode::VotingWeightChanged
+        );
+
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.proposal = ctx.accounts.proposal.key();
+        vote_record.voter = ctx.accounts.voter.key();
+        vote_record.bump = ctx.bumps.vote_record;
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.voter.key(),
+            ctx.accounts.proposal.key(),
+            ctx.accounts.proposal.pool,
+            Circuit::AddWeightedVote,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Votes land one at a time, each on top of the last tally, and the
+        // contribution they are weighted by can't move until this one lands
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.proposal.lock(request)?;
+        ctx.accounts.confidential_member.lock(request)?;
+
+        let args = vote.args(ArgBuilder::new());
+        let args = ctx.accounts.confidential_member.contribution_args(args);
+        let args = ctx.accounts.proposal.tally_args(args).build();
+
+        let callback = AddWeightedVoteCallback::callback_ix(&[
+            CallbackAccount {
+                pubkey: ctx.accounts.mpc_request.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.proposal.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.vote_record.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.confidential_member.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.voter.key(),
+                is_writable: true,
+            },
+        ]);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Confidential vote queued on proposal {}",
+            ctx.accounts.proposal.proposal_id
+        );
+        msg!("Vote: [ENCRYPTED - PRIVATE]");
+        Ok(())
+    }
+
+    /// MPC callback for a confidential vote
+    #[arcium_callback(encrypted_ix = "add_weighted_vote")]
+    pub fn add_weighted_vote_callback(
+        ctx: Context<AddWeightedVoteCallback>,
+        output: ComputationOutputs<AddWeightedVoteOutput>,
+    ) -> Result<()> {
+        // Release the lock whatever the outcome
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.proposal.unlock(request);
+        ctx.accounts.confidential_member.unlock(request);
+
+        let new_tally = match output {
+            ComputationOutputs::Success(AddWeightedVoteOutput { field_0 }) => field_0,
+            _ => {
+                // Not counted, so let the member vote again
+                ctx.accounts
+                    .vote_record
+                    .close(ctx.accounts.voter.to_account_info())?;
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.voter.to_account_info(),
+                );
+            }
+        };
+
+        ctx.accounts.proposal.store_tally(&new_tally);
+        ctx.accounts.proposal.vote_count += 1;
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.voter.to_account_info())?;
+
+        msg!(
+            "Confidential vote counted on proposal {} ({} votes)",
+            ctx.accounts.proposal.proposal_id,
+            ctx.accounts.proposal.vote_count
+        );
+        Ok(())
+    }
+
+    /// Tally a confidential proposal once voting has closed. Anyone can
+    /// call this; only pass/fail and turnout are revealed.
+    pub fn tally_confidential_votes(
+        ctx: Context<TallyConfidentialVotes>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            Clock::get()?.slot >= ctx.accounts.proposal.deadline_slot,
+            ErrorCode::VotingStillOpen
+        );
+
+        ctx.accounts.mpc_request.open(
+            ctx.accounts.payer.key(),
+            ctx.accounts.proposal.key(),
+            ctx.accounts.confidential_pool.key(),
+            Circuit::TallyVotes,
+            ctx.bumps.mpc_request,
+        )?;
+        ctx.accounts
+            .mpc_request_counter
+            .advance(ctx.bumps.mpc_request_counter);
+
+        // Wait for any vote still in flight, and keep late ones out
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.proposal.lock(request)?;
+
+        let args = ctx.accounts.proposal.tally_args(ArgBuilder::new());
+        let args = ctx.accounts.proposal.total_args(args);
+        let args = args.plaintext_u16(ctx.accounts.proposal.quorum_bps).build();
+
+        let callback = TallyVotesCallback::callback_ix(&[
+            CallbackAccount {
+                pubkey: ctx.accounts.mpc_request.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.proposal.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.payer.key(),
+                is_writable: true,
+            },
+        ]);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        queue_computation(ctx.accounts, computation_offset, args, None, vec![callback])?;
+
+        msg!(
+            "Confidential tally queued for proposal {}",
+            ctx.accounts.proposal.proposal_id
+        );
+        Ok(())
+    }
+
+    /// MPC callback for a confidential tally; writes the outcome to the proposal
+    #[arcium_callback(encrypted_ix = "tally_votes")]
+    pub fn tally_votes_callback(
+        ctx: Context<TallyVotesCallback>,
+        output: ComputationOutputs<TallyVotesOutput>,
+    ) -> Result<()> {
+        let request = ctx.accounts.mpc_request.key();
+        ctx.accounts.proposal.unlock(request);
+
+        let (passed, turnout_bps) = match output {
+            ComputationOutputs::Success(TallyVotesOutput {
+                field_0:
+                    TallyVotesOutputStruct0 {
+                        field_0: passed,
+                        field_1: turnout_bps,
+                    },
+            }) => (passed, turnout_bps),
+            // The proposal stays in `Voting`, so the tally can be queued again
+            _ => {
+                return fail_mpc_request(
+                    &mut ctx.accounts.mpc_request,
+                    ctx.accounts.payer.to_account_info(),
+                )
+            }
+        };
+        // Votes only come from stakes counted in the snapshot, so more than
+        // the whole pool voting means the tally can't be trusted
+        require!(
+            turnout_bps <= MAX_BASIS_POINTS as u64,
+            ErrorCode::TurnoutExceedsPool
+        );
+        let turnout_bps = turnout_bps as u16;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.status = if passed {
+            ProposalStatus::Passed
+        } else {
+            ProposalStatus::Rejected
+        };
+        proposal.turnout_bps = turnout_bps;
+
+        emit!(ConfidentialProposalTallied {
+            proposal: proposal.key(),
+            status: proposal.status,
+            turnout_bps,
+        });
+
+        ctx.accounts.mpc_request.settle(MpcRequestStatus::Completed)?;
+        ctx.accounts
+            .mpc_request
+            .close(ctx.accounts.payer.to_account_info())?;
+
+        msg!(
+            "Confidential proposal {} tallied: {:?}, turnout {} bps",
+            ctx.accounts.proposal.proposal_id,
+            ctx.accounts.proposal.status,
+            turnout_bps
+        );
+        Ok(())
+    }
+
+    // ========================================================================
+    // MPC REQUESTS
+    // ========================================================================
+
+    /// Mark a computation that never called back as `Failed` once the
+    /// configured timeout has passed. Anyone can call this.
+    pub fn expire_mpc_request(ctx: Context<ExpireMpcRequest>) -> Result<()> {
+        let request = &mut ctx.accounts.mpc_request;
+        let timeout = ctx.accounts.oracle_config.mpc_timeout_slots;
+        require!(
+            Clock::get()?.slot >= request.created_slot.saturating_add(timeout),
+            ErrorCode::MpcRequestNotExpired
+        );
+
+        request.settle(MpcRequestStatus::Failed)?;
+
+        // Unlock the accounts the computation was holding
+        require!(
+            ctx.accounts.confidential_deposit.is_some() == request.circuit.locks_deposit()
+                && ctx.accounts.confidential_pool.is_some() == request.circuit.locks_pool()
+                && ctx.accounts.confidential_proposal.is_some()
+                    == request.circuit.locks_proposal()
+                && ctx.accounts.confidential_member.is_some() == request.circuit.locks_member()
+                && ctx.accounts.vote_record.is_some()
+                    == (request.circuit == Circuit::AddWeightedVote),
+            ErrorCode::MpcRequestMismatch
+        );
+        if let Some(deposit) = &mut ctx.accounts.confidential_deposit {
+            deposit.unlock(request.key());
+        }
+        if let Some(pool) = &mut ctx.accounts.confidential_pool {
+            pool.unlock(request.key());
+        }
+        if let Some(proposal) = &mut ctx.accounts.confidential_proposal {
+            proposal.unlock(request.key());
+        }
+        if let Some(member) = &mut ctx.accounts.confidential_member {
+            // Only the member this request locked can be passed, so another
+            // member's position can't stand in and leave theirs locked
+            require!(
+                member.pending_request == Some(request.key()),
+                ErrorCode::MpcRequestMismatch
+            );
+            member.unlock(request.key());
+        }
+        // The vote was never counted, so let the member vote again
+        if let Some(vote_record) = &ctx.accounts.vote_record {
+            vote_record.close(ctx.accounts.requester.to_account_info())?;
+        }
+
+        emit!(MpcRequestFailed {
+            request: request.key(),
+            requester: request.requester,
+            expired: true,
+        });
+
+        msg!("MPC request expired: {}", request.key());
+        close_settled_request(request, ctx.accounts.requester.to_account_info())
+    }
+
+    /// Close requests left open by older program versions, returning their
+    /// rent to the requester. Pass the requests as remaining accounts.
+    ///
+    /// Those versions never settled a request, so one still `Pending` is
+    /// closed once it is past the MPC timeout. Current requests close
+    /// themselves on callback, expiry or refund and are refused here.
+    pub fn reclaim_mpc_requests<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReclaimMpcRequests<'info>>,
+    ) -> Result<()> {
+        let requester = ctx.accounts.requester.to_account_info();
+        let timeout = ctx.accounts.oracle_config.mpc_timeout_slots;
+        let slot = Clock::get()?.slot;
+
+        for info in ctx.remaining_accounts {
+            if let Ok(request) = Account::<MpcRequest>::try_from(info) {
+                require!(
+                    request.status != MpcRequestStatus::Pending,
+                    ErrorCode::MpcRequestNotSettled
+                );
+                // The only settled request left open is a failed deposit
+                return err!(ErrorCode::MpcRequestHasEscrow);
+            }
+
+            let request = LegacyMpcRequest::try_from_account(info)?;
+            require_keys_eq!(request.requester, requester.key(), ErrorCode::Unauthorized);
+            require!(
+                request.status != MpcRequestStatus::Pending
+                    || slot >= request.created_slot.saturating_add(timeout),
+                ErrorCode::MpcRequestNotExpired
+            );
+
+            close_legacy_request(info, &requester)?;
+        }
+
+        msg!(
+            "Reclaimed {} MPC requests for {}",
+            ctx.remaining_accounts.len(),
+            requester.key()
+        );
+        Ok(())
+    }
+}
+
+// ========================================================================
+// HELPER FUNCTIONS
+// ========================================================================
+
+/// Verify an oracle authorization end to end: not expired, signed by enough
+/// oracles over its exact Borsh encoding, and carrying an unused nonce
+fn verify_oracle_authorization(
+    oracle_config: &Account<OracleConfig>,
+    instructions_sysvar: &AccountInfo,
+    authorization: &OracleAuthorization,
+    next_nonce: &mut u64,
+) -> Result<()> {
+    require!(
+        Clock::get()?.slot <= authorization.expiry_slot,
+        ErrorCode::OracleAuthorizationExpired
+    );
+
+    let mut message = Vec::new();
+    authorization
+        .serialize(&mut message)
+        .map_err(|_| ErrorCode::InvalidOracleSignature)?;
+
+    verify_oracle_signature(oracle_config, instructions_sysvar, &message)?;
+    consume_oracle_nonce(next_nonce, authorization.nonce)
+}
+
+/// Reject oracle nonces that were already used and advance the counter past
+/// the one just consumed, so every signed authorization works at most once
+fn consume_oracle_nonce(next_nonce: &mut u64, nonce: u64) -> Result<()> {
+    require!(nonce >= *next_nonce, ErrorCode::StaleOracleNonce);
+    *next_nonce = nonce.checked_add(1).ok_or(ErrorCode::StaleOracleNonce)?;
+    Ok(())
+}
+
+/// Ed25519 instruction layout: [num_signatures: u8, padding: u8] followed by
+/// one 14-byte offsets record per signature
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_SIZE: usize = 14;
+
+/// Verify that Ed25519 program instructions earlier in this transaction sign
+/// exactly `message` with at least `threshold` distinct configured oracle keys.
+///
+/// The Ed25519 precompile has already checked the signatures by the time we
+/// run, so all that's left is to make sure they cover the right keys and message.
+fn verify_oracle_signature(
+    oracle_config: &Account<OracleConfig>,
+    instructions_sysvar: &AccountInfo,
+    message: &[u8],
+) -> Result<()> {
+    let current_index = ix_sysvar::load_current_index_checked(instructions_sysvar)?;
+    let mut signers: Vec<Pubkey> = Vec::with_capacity(oracle_config.oracle_keys.len());
+
+    for index in 0..current_index {
+        let ix = ix_sysvar::load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        for signer in ed25519_instruction_signers(&ix.data, index, message) {
+            if oracle_config.oracle_keys.contains(&signer) && !signers.contains(&signer) {
+                signers.push(signer);
+            }
+        }
+    }
+
+    if signers.len() < oracle_config.threshold as usize {
+        msg!(
+            "Oracle signatures: {} of {} required",
+            signers.len(),
+            oracle_config.threshold
+        );
+        return err!(ErrorCode::InvalidOracleSignature);
+    }
+    Ok(())
+}
+
+/// Collect the public keys that an Ed25519 instruction verified over `message`.
+/// Offsets pointing at another instruction are skipped, since the bytes we
+/// compare against would not be what was verified.
+fn ed25519_instruction_signers(data: &[u8], ix_index: u16, message: &[u8]) -> Vec<Pubkey> {
+    let Some(&num_signatures) = data.first() else {
+        return Vec::new();
+    };
+
+    (0..num_signatures as usize)
+        .filter_map(|i| {
+            let start = ED25519_OFFSETS_START + i * ED25519_OFFSETS_SIZE;
+            let offsets = data.get(start..start + ED25519_OFFSETS_SIZE)?;
+            let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+            let signature_ix = read_u16(2);
+            let pubkey_offset = read_u16(4) as usize;
+            let pubkey_ix = read_u16(6);
+            let message_offset = read_u16(8) as usize;
+            let message_size = read_u16(10) as usize;
+            let message_ix = read_u16(12);
+
+            let in_this_ix = |idx: u16| idx == u16::MAX || idx == ix_index;
+            if !(in_this_ix(signature_ix) && in_this_ix(pubkey_ix) && in_this_ix(message_ix)) {
+                return None;
+            }
+            if data.get(message_offset..message_offset + message_size) != Some(message) {
+                return None;
+            }
+
+            let pubkey = data.get(pubkey_offset..pubkey_offset + 32)?;
+            Pubkey::try_from(pubkey).ok()
+        })
+        .collect()
+}
+
+/// Start the timelock on a change to the oracle signer set. Only one change
+/// can be pending at a time.
+fn propose_oracle_change(oracle_config: &mut OracleConfig, change: OracleChange) -> Result<()> {
+    require!(
+        oracle_config.pending_oracle.is_none(),
+        ErrorCode::ProposalAlreadyPending
+    );
+    oracle_config.check(change)?;
+
+    let activation_slot = Clock::get()?.slot + ORACLE_TIMELOCK_SLOTS;
+    oracle_config.pending_oracle = Some(change);
+    oracle_config.oracle_activation_slot = activation_slot;
+
+    emit!(OracleProposed {
+        change,
+        activation_slot,
+    });
+    msg!("Oracle change proposed: {:?} (active from slot {})", change, activation_slot);
+    Ok(())
+}
+
+/// Settle a request whose computation aborted or whose balance check failed
+/// inside the circuit, and return its rent to the requester. The stored
+/// ciphertexts are left as they were.
+fn fail_mpc_request<'info>(
+    mpc_request: &mut Account<'info, MpcRequest>,
+    requester: AccountInfo<'info>,
+) -> Result<()> {
+    mpc_request.settle(MpcRequestStatus::Failed)?;
+
+    emit!(MpcRequestFailed {
+        request: mpc_request.key(),
+        requester: mpc_request.requester,
+        expired: false,
+    });
+
+    msg!("MPC request failed: {}", mpc_request.key());
+    close_settled_request(mpc_request, requester)
+}
+
+/// Close a settled request back to its requester. A failed deposit stays
+/// open instead, since `refund_confidential_deposit` needs it to find the
+/// escrow, and is closed by the refund.
+fn close_settled_request<'info>(
+    mpc_request: &Account<'info, MpcRequest>,
+    requester: AccountInfo<'info>,
+) -> Result<()> {
+    if mpc_request.status == MpcRequestStatus::Failed && mpc_request.circuit.escrows_deposit() {
+        return Ok(());
+    }
+    mpc_request.close(requester)
+}
+
+/// Require that a pool vault is still owned by `owner` and that nobody else
+/// can move or close it
+fn check_vault_unchanged(vault: &TokenAccount, owner: Pubkey) -> Result<()> {
+    require!(
+        vault.owner == owner && vault.delegate.is_none() && vault.close_authority.is_none(),
+        ErrorCode::SwapVaultAltered
+    );
+    Ok(())
+}
+
+/// Close an account `reclaim_mpc_requests` decoded by hand, the way
+/// Anchor's `close` does for typed accounts
+fn close_legacy_request<'info>(
+    info: &AccountInfo<'info>,
+    requester: &AccountInfo<'info>,
+) -> Result<()> {
+    let lamports = info.lamports();
+    **requester.try_borrow_mut_lamports()? += lamports;
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&system_program::ID);
+    info.resize(0)?;
+    Ok(())
+}
+
+/// Wrap `lamports` from `from` into the native token account `wsol_account`
+fn wrap_sol<'info>(
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    wsol_account: AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from,
+                to: wsol_account.clone(),
+            },
+        ),
+        lamports,
+    )?;
+    token_interface::sync_native(CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative {
+            account: wsol_account,
+        },
+    ))
+}
+
+/// Accounts passed to `add_to_balance_callback`, in the order
+/// `AddToBalanceCallback` expects them
+fn deposit_callback_accounts(
+    mpc_request: Pubkey,
+    confidential_deposit: Pubkey,
+    deposit_escrow: Pubkey,
+    user_vault: Pubkey,
+    user: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Vec<CallbackAccount> {
+    let mut accounts: Vec<CallbackAccount> = [
+        mpc_request,
+        confidential_deposit,
+        deposit_escrow,
+        user_vault,
+        user,
+    ]
+    .into_iter()
+    .map(|pubkey| CallbackAccount {
+        pubkey,
+        is_writable: true,
+    })
+    .collect();
+    accounts.extend([mint, token_program].map(|pubkey| CallbackAccount {
+        pubkey,
+        is_writable: false,
+    }));
+    accounts
+}
+
+/// Move everything in a deposit escrow to `destination` and close it,
+/// returning its rent to `rent_receiver`. Returns the amount `destination`
+/// actually received.
+fn drain_deposit_escrow<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    escrow_bump: u8,
+    mpc_request: Pubkey,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &mut InterfaceAccount<'info, TokenAccount>,
+    rent_receiver: AccountInfo<'info>,
+) -> Result<u64> {
+    let seeds: &[&[u8]] = &[b"deposit_escrow", mpc_request.as_ref(), &[escrow_bump]];
+
+    let received = transfer_tokens(
+        token_program,
+        escrow,
+        destination,
+        mint,
+        escrow.to_account_info(),
+        &[seeds],
+        escrow.amount,
+    )?;
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow.to_account_info(),
+            destination: rent_receiver,
+            authority: escrow.to_account_info(),
+        },
+        &[seeds],
+    ))?;
+
+    Ok(received)
+}
+
+/// `transfer_checked` between two token accounts of `mint`, returning the
+/// amount `to` actually received (less any Token-2022 transfer fee)
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = to.amount;
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority,
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )?;
+    to.reload()?;
+
+    Ok(to.amount.saturating_sub(balance_before))
+}
+
+/// Reject mints carrying Token-2022 extensions the vault accounting can't
+/// follow. Transfer fees are only allowed where the caller credits the net
+/// received amount.
+fn check_mint_extensions(mint: &InterfaceAccount<Mint>, allow_transfer_fee: bool) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner == anchor_spl::token::ID {
+        return Ok(());
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        let supported = match extension {
+            ExtensionType::MetadataPointer
+            | ExtensionType::TokenMetadata
+            | ExtensionType::GroupPointer
+            | ExtensionType::TokenGroup
+            | ExtensionType::GroupMemberPointer
+            | ExtensionType::TokenGroupMember
+            | ExtensionType::MintCloseAuthority
+            | ExtensionType::InterestBearingConfig => true,
+            ExtensionType::TransferFeeConfig => allow_transfer_fee,
+            // Hooks are allowed only once disabled: the vault transfers
+            // don't forward extra hook accounts
+            ExtensionType::TransferHook => {
+                Option::<Pubkey>::from(state.get_extension::<TransferHook>()?.program_id).is_none()
+            }
+            _ => false,
+        };
+        if !supported {
+            msg!("Unsupported mint extension: {:?}", extension);
+            return err!(ErrorCode::UnsupportedMintExtension);
+        }
+    }
+
+    Ok(())
+}
+
+/// Accounts passed to the pool transfer and settlement callbacks, in the
+/// order their `callback_accounts` structs expect them. `requester` gets the
+/// request's rent back.
+fn pool_transfer_callback_accounts(
+    mpc_request: Pubkey,
+    confidential_deposit: Pubkey,
+    user_vault: Pubkey,
+    confidential_pool: Pubkey,
+    group_vault: Pubkey,
+    confidential_member: Pubkey,
+    requester: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+) -> Vec<CallbackAccount> {
+    let mut accounts: Vec<CallbackAccount> = [
+        mpc_request,
+        confidential_deposit,
+        user_vault,
+        confidential_pool,
+        group_vault,
+        confidential_member,
+        requester,
+    ]
+    .into_iter()
+    .map(|pubkey| CallbackAccount {
+        pubkey,
+        is_writable: true,
+    })
+    .collect();
+    accounts.extend([mint, token_program].map(|pubkey| CallbackAccount {
+        pubkey,
+        is_writable: false,
+    }));
+    accounts
+}
+
+// ========================================================================
+// ACCOUNT STRUCTURES
+// ========================================================================
+
+#[account]
+pub struct OracleConfig {
+    pub authority: Pubkey,
+    pub oracle_keys: Vec<Pubkey>, // Up to MAX_ORACLES
+    pub threshold: u8,            // Distinct oracle signatures required
+    pub pending_oracle: Option<OracleChange>, // Applied by `accept_oracle`
+    pub oracle_activation_slot: u64,
+    pub pending_authority: Option<Pubkey>,
+    pub authority_activation_slot: u64,
+    pub cluster: OracleCluster, // Signed into every authorization
+    pub mpc_timeout_slots: u64, // After this, a pending MPC request can be expired
+    pub bump: u8,
+}
+
+impl OracleConfig {
+    /// Reject a change that would leave the signer set unusable
+    pub fn check(&self, change: OracleChange) -> Result<()> {
+        match change {
+            OracleChange::Add(key) => {
+                require!(
+                    self.oracle_keys.len() < MAX_ORACLES,
+                    ErrorCode::TooManyOracles
+                );
+                require!(
+                    !self.oracle_keys.contains(&key),
+                    ErrorCode::OracleAlreadyRegistered
+                );
+            }
+            OracleChange::Remove(key) => {
+                require!(self.oracle_keys.contains(&key), ErrorCode::OracleNotFound);
+                // Never leave fewer keys than signatures required
+                require!(
+                    self.oracle_keys.len() > self.threshold as usize,
+                    ErrorCode::InvalidOracleThreshold
+                );
+            }
+            OracleChange::SetThreshold(threshold) => {
+                require!(
+                    threshold > 0 && threshold as usize <= self.oracle_keys.len(),
+                    ErrorCode::InvalidOracleThreshold
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, change: OracleChange) -> Result<()> {
+        self.check(change)?;
+        match change {
+            OracleChange::Add(key) => self.oracle_keys.push(key),
+            OracleChange::Remove(key) => self.oracle_keys.retain(|oracle| *oracle != key),
+            OracleChange::SetThreshold(threshold) => self.threshold = threshold,
+        }
+        Ok(())
+    }
+}
+
+#[account]
+pub struct UserDeposit {
+    pub owner: Pubkey,
+    pub mint: Pubkey, // Token held in `user_vault`, part of the seeds
+    pub deposited_amount: u64,
+    pub oracle_nonce: u64, // Next nonce the oracle may sign for this user
+    pub bump: u8,
+    pub vault_bump: u8, // `user_vault` token account
+}
+
+#[account]
+pub struct GroupPool {
+    pub admin: Pubkey,
+    pub mint: Pubkey, // Token held in `group_vault`, part of the seeds
+    pub group_id: String,
+    pub group_name: String,
+    pub total_locked: u64, // Sum of members' `contributed_amount`
+    pub total_shares: u64, // Sum of members' `shares`
+    pub net_asset_value: u64, // `group_vault` balance as moved by the program, see `nav`
+    pub is_active: bool,
+    pub open_positions: Vec<Pubkey>, // Mints held in `pool_token_vault`s, up to MAX_POOL_POSITIONS
+    pub bump: u8,
+    pub vault_bump: u8, // `group_vault` token account
+}
+
+/// Share accounting for public pools. The pool's NAV is what its
+/// `group_vault` holds through deposits, exits and swaps; shares are minted
+/// and burned at NAV so gains and losses are split by ownership, not by who
+/// joined when.
+impl GroupPool {
+    /// The pool's NAV. Tokens sent straight to the vault aren't counted, so
+    /// nobody can move the share price without going through the program.
+    /// Bought tokens have no price on-chain, so there is none while any are
+    /// held; members enter and exit once they are sold.
+    pub fn nav(&self) -> Result<u64> {
+        require!(
+            self.open_positions.is_empty(),
+            ErrorCode::PoolHasOpenPositions
+        );
+        Ok(self.net_asset_value)
+    }
+
+    /// Shares to mint for `amount` added to a pool worth `nav` before the
+    /// deposit. The first deposit mints one share per token.
+    pub fn shares_for_deposit(&self, amount: u64, nav: u64) -> Result<u64> {
+        if self.total_shares == 0 {
+            return Ok(amount);
+        }
+        require!(nav > 0, ErrorCode::PoolValueLost);
+        Ok((amount as u128 * self.total_shares as u128 / nav as u128) as u64)
+    }
+
+    /// What `shares` are worth in a pool worth `nav`
+    pub fn value_of(&self, shares: u64, nav: u64) -> u64 {
+        if self.total_shares == 0 {
+            return 0;
+        }
+        (shares as u128 * nav as u128 / self.total_shares as u128) as u64
+    }
+
+    /// Shares to burn to pay out `amount`, rounded up so an exit never
+    /// takes value from the members who stay
+    pub fn shares_for_withdrawal(&self, amount: u64, nav: u64) -> u64 {
+        (amount as u128 * self.total_shares as u128).div_ceil(nav as u128) as u64
+    }
+
+    /// Burn a member's `shares` for `paid` out of the vault, along with the
+    /// same fraction of their contribution. An emptied pool starts over at
+    /// one share per token, leaving any rounding dust uncounted.
+    pub fn burn(&mut self, position: &mut MemberPosition, shares: u64, paid: u64) {
+        let released = (position.contributed_amount as u128 * shares as u128
+            / position.shares as u128) as u64;
+        position.shares -= shares;
+        position.contributed_amount -= released;
+        self.total_shares -= shares;
+        self.total_locked -= released;
+        self.net_asset_value = if self.total_shares == 0 {
+            0
+        } else {
+            self.net_asset_value - paid
+        };
+    }
+}
+
+/// A member's stake in a public group pool. `contributed_amount` is their
+/// voting weight; `shares` are what they own of the pool.
+#[account]
+pub struct MemberPosition {
+    pub pool: Pubkey,
+    pub member: Pubkey,
+    pub contributed_amount: u64, // Net amount released into the pool
+    pub shares: u64,             // Units of the pool's `total_shares`
+    pub last_increase_slot: u64, // Slot of the last release into the pool
+    pub bump: u8,
+}
+
+/// Returned by `get_member_position`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct MemberStake {
+    pub pool: Pubkey,
+    pub member: Pubkey,
+    pub contributed_amount: u64,
+    pub shares: u64,
+    pub entitlement: u64, // `shares` valued at the pool's current NAV
+    pub pool_total_locked: u64,
+    pub pool_total_shares: u64,
+    pub pool_nav: u64,
+}
+
+/// Basis-point denominator for proposal quorums
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+#[account]
+pub struct Proposal {
+    pub pool: Pubkey,
+    pub proposal_id: u64, // Chosen by the proposer, part of the seeds
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub token: Pubkey, // Token to buy or sell
+    pub amount: u64,
+    pub recipient: Pubkey,  // Member paid by a `Release`, default otherwise
+    pub created_slot: u64,  // Positions topped up from here on can't vote
+    pub deadline_slot: u64, // Votes are accepted before this slot
+    pub quorum_bps: u16,    // Share of `total_weight` that must vote
+    pub total_weight: u64,  // Pool's `total_locked` when the proposal opened
+    pub yes_weight: u64,
+    pub no_weight: u64,
+    pub status: ProposalStatus,
+    pub bump: u8,
+}
+
+impl Proposal {
+    /// Passed if turnout meets the quorum and yes outweighs no
+    pub fn outcome(&self) -> ProposalStatus {
+        let turnout = self.yes_weight as u128 + self.no_weight as u128;
+        let quorum = self.total_weight as u128 * self.quorum_bps as u128
+            / MAX_BASIS_POINTS as u128;
+        if turnout >= quorum && self.yes_weight > self.no_weight {
+            ProposalStatus::Passed
+        } else {
+            ProposalStatus::Rejected
+        }
+    }
+}
+
+/// One member's vote; its PDA existing is what stops a second vote
+#[account]
+pub struct VoteRecord {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub approve: bool,
+    pub weight: u64,
+    pub bump: u8,
+}
+
+/// Trading session on a public group pool; one per pool, reused for each
+/// new session once the last one has closed
+#[account]
+pub struct Session {
+    pub pool: Pubkey,
+    pub session_id: u64, // Counts sessions on this pool, starting at 0
+    pub start_slot: u64, // Releases into the pool stop here
+    pub end_slot: u64,   // Trading can be settled from here
+    pub status: SessionStatus,
+    pub bump: u8,
+}
+
+impl Session {
+    /// Whether members can still release funds into the pool
+    pub fn accepts_releases(&self, slot: u64) -> bool {
+        self.status == SessionStatus::Open && slot < self.start_slot
+    }
+
+    /// Whether funds can leave the pool, i.e. it is outside a lock window
+    pub fn funds_unlocked(&self, slot: u64) -> bool {
+        self.status == SessionStatus::Closed || self.accepts_releases(slot)
+    }
+
+    /// Whether the pool can trade: buys only while the session is
+    /// trading, sells until it closes so positions can be unwound
+    pub fn accepts_swap(&self, action: ProposalAction, slot: u64) -> bool {
+        match self.status {
+            SessionStatus::Trading => slot < self.end_slot || action == ProposalAction::Sell,
+            SessionStatus::Settling => action == ProposalAction::Sell,
+            _ => false,
+        }
+    }
+
+    /// The state `advance_session` moves to from here
+    pub fn next_status(&self, slot: u64, is_admin: bool) -> Result<SessionStatus> {
+        let (next, allowed) = match self.status {
+            SessionStatus::Open => (SessionStatus::Locked, is_admin || slot >= self.start_slot),
+            SessionStatus::Locked => (SessionStatus::Trading, is_admin),
+            SessionStatus::Trading => (SessionStatus::Settling, is_admin || slot >= self.end_slot),
+            SessionStatus::Settling => (SessionStatus::Closed, is_admin),
+            SessionStatus::Closed => (SessionStatus::Closed, false),
+        };
+        require!(allowed, ErrorCode::SessionTransitionNotAllowed);
+        Ok(next)
+    }
+}
+
+/// DEX programs `execute_pool_swap` may hand the group vault's signature to,
+/// managed by the oracle authority
+#[account]
+pub struct SwapAllowlist {
+    pub programs: Vec<Pubkey>, // Up to MAX_SWAP_PROGRAMS
+    pub bump: u8,
+}
+
+// CONFIDENTIAL STRUCTURES
+
+#[account]
+pub struct ConfidentialUserDeposit {
+    pub owner: Pubkey,
+    pub mint: Pubkey, // Token held in `confidential_vault`, part of the seeds
+    pub encryption_pubkey: [u8; 32], // Owner's x25519 key
+    pub balance_nonce: u128,
+    pub encrypted_balance: [u8; 32], // ENCRYPTED - Enc<Shared, u64>
+    pub balance_initialized: bool,   // False until the first MPC write
+    pub last_update_slot: u64,
+    pub is_active: bool,
+    pub oracle_nonce: u64, // Next nonce the oracle may sign for this user
+    pub pending_request: Option<Pubkey>, // MPC request currently holding the balance
+    pub bump: u8,
+    pub vault_bump: u8, // `confidential_vault` token account
+}
+
+impl ConfidentialUserDeposit {
+    /// Reserve the balance for `request` until its callback or expiry
+    pub fn lock(&mut self, request: Pubkey) -> Result<()> {
+        require!(self.pending_request.is_none(), ErrorCode::OperationPending);
+        self.pending_request = Some(request);
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, request: Pubkey) {
+        if self.pending_request == Some(request) {
+            self.pending_request = None;
+        }
+    }
+
+    /// Append the stored balance as an `Enc<Shared, u64>` circuit argument,
+    /// followed by its initialized flag
+    pub fn balance_args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.x25519_pubkey(self.encryption_pubkey)
+            .plaintext_u128(self.balance_nonce)
+            .encrypted_u64(self.encrypted_balance)
+            .plaintext_bool(self.balance_initialized)
+    }
+
+    /// Write back a balance re-encrypted by the MPC
+    pub fn store_balance(&mut self, balance: &SharedEncryptedStruct<1>) -> Result<()> {
+        require!(
+            balance.encryption_key == self.encryption_pubkey,
+            ErrorCode::InvalidEncryptedResult
+        );
+        self.balance_nonce = balance.nonce;
+        self.encrypted_balance = balance.ciphertexts[0];
+        self.balance_initialized = true;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct ConfidentialGroupPool {
+    pub admin: Pubkey,
+    pub mint: Pubkey, // Token held in `confidential_group_vault`, part of the seeds
     pub group_id: String,
     pub group_name: String,
-    pub encrypted_total: [u8; 32], // ENCRYPTED - private
-    pub participant_count: u32, // PUBLIC
+    pub total_nonce: u128,
+    pub encrypted_total: [u8; 32], // ENCRYPTED - Enc<Mxe, u64>
+    pub total_initialized: bool,   // False until the first MPC write
+    pub participants_nonce: u128,
+    pub encrypted_participants: [u8; 32], // ENCRYPTED - Enc<Mxe, u64>, written with the total
+    pub participant_count: u32, // PUBLIC - members with a stake, as of the last `compute_pool_stats`
     pub is_active: bool,
     pub meets_minimum_threshold: bool,
+    pub pending_request: Option<Pubkey>, // MPC request currently holding the total
+    pub bump: u8,
+    pub vault_bump: u8, // `confidential_group_vault` token account
+}
+
+impl ConfidentialGroupPool {
+    /// Reserve the total for `request` until its callback or expiry
+    pub fn lock(&mut self, request: Pubkey) -> Result<()> {
+        require!(self.pending_request.is_none(), ErrorCode::OperationPending);
+        self.pending_request = Some(request);
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, request: Pubkey) {
+        if self.pending_request == Some(request) {
+            self.pending_request = None;
+        }
+    }
+
+    /// Append the stored total as an `Enc<Mxe, u64>` circuit argument,
+    /// followed by its initialized flag and the encrypted participant count
+    pub fn total_args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.plaintext_u128(self.total_nonce)
+            .encrypted_u64(self.encrypted_total)
+            .plaintext_bool(self.total_initialized)
+            .plaintext_u128(self.participants_nonce)
+            .encrypted_u64(self.encrypted_participants)
+    }
+
+    /// Write back a total and participant count re-encrypted by the MPC
+    pub fn store_total(
+        &mut self,
+        total: &MXEEncryptedStruct<1>,
+        participants: &MXEEncryptedStruct<1>,
+    ) {
+        self.total_nonce = total.nonce;
+        self.encrypted_total = total.ciphertexts[0];
+        self.participants_nonce = participants.nonce;
+        self.encrypted_participants = participants.ciphertexts[0];
+        self.total_initialized = true;
+    }
+}
+
+/// Amount encrypted by the caller for the MPC as `Enc<Shared, u64>`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EncryptedAmount {
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertext: [u8; 32],
+}
+
+impl EncryptedAmount {
+    pub fn args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.x25519_pubkey(self.encryption_pubkey)
+            .plaintext_u128(self.nonce)
+            .encrypted_u64(self.ciphertext)
+    }
+
+    /// What the oracle signs in place of the amount, binding its
+    /// authorization to this exact ciphertext
+    pub fn commitment(&self) -> [u8; 32] {
+        hashv(&[
+            &self.encryption_pubkey,
+            &self.nonce.to_le_bytes(),
+            &self.ciphertext,
+        ])
+        .to_bytes()
+    }
+}
+
+/// Yes/no vote encrypted by the voter for the MPC as `Enc<Shared, bool>`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EncryptedVote {
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertext: [u8; 32],
+}
+
+impl EncryptedVote {
+    pub fn args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.x25519_pubkey(self.encryption_pubkey)
+            .plaintext_u128(self.nonce)
+            .encrypted_bool(self.ciphertext)
+    }
+}
+
+/// A member's encrypted stake in a dark pool, kept in step by the pool
+/// transfer callbacks and used as their confidential voting weight
+#[account]
+pub struct ConfidentialMemberPosition {
+    pub pool: Pubkey,
+    pub member: Pubkey,
+    pub contribution_nonce: u128,
+    pub encrypted_contribution: [u8; 32], // ENCRYPTED - Enc<Mxe, u64>
+    pub contribution_initialized: bool,   // False until the first MPC write
+    pub pending_request: Option<Pubkey>, // MPC request currently holding the contribution
+    pub last_update_slot: u64, // Last slot the contribution was rewritten
+    pub bump: u8,
+}
+
+impl ConfidentialMemberPosition {
+    /// Reserve the contribution for `request` until its callback or expiry
+    pub fn lock(&mut self, request: Pubkey) -> Result<()> {
+        require!(self.pending_request.is_none(), ErrorCode::OperationPending);
+        self.pending_request = Some(request);
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, request: Pubkey) {
+        if self.pending_request == Some(request) {
+            self.pending_request = None;
+        }
+    }
+
+    /// Append the stored contribution as an `Enc<Mxe, u64>` circuit
+    /// argument, followed by its initialized flag
+    pub fn contribution_args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.plaintext_u128(self.contribution_nonce)
+            .encrypted_u64(self.encrypted_contribution)
+            .plaintext_bool(self.contribution_initialized)
+    }
+
+    /// Write back a contribution re-encrypted by the MPC
+    pub fn store_contribution(&mut self, contribution: &MXEEncryptedStruct<1>) {
+        self.contribution_nonce = contribution.nonce;
+        self.encrypted_contribution = contribution.ciphertexts[0];
+        self.contribution_initialized = true;
+    }
+}
+
+/// Proposal on a dark pool. Votes and their weights stay encrypted; only
+/// the outcome and turnout are written back by `tally_votes`.
+#[account]
+pub struct ConfidentialProposal {
+    pub pool: Pubkey,
+    pub proposal_id: u64, // Chosen by the proposer, part of the seeds
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub created_slot: u64,
+    pub deadline_slot: u64, // Votes are accepted before this slot
+    pub quorum_bps: u16,    // Share of the pool total that must vote
+    pub total_nonce: u128,
+    pub encrypted_total: [u8; 32], // ENCRYPTED - Enc<Mxe, u64>, pool total at `created_slot`
+    pub total_initialized: bool,
+    pub tally_nonce: u128,
+    pub encrypted_tally: [[u8; 32]; 2], // ENCRYPTED - Enc<Mxe, VoteTally>
+    pub tally_initialized: bool,        // False until the first vote lands
+    pub vote_count: u32,                // PUBLIC
+    pub turnout_bps: u16,               // Revealed by the tally
+    pub status: ProposalStatus,
+    pub pending_request: Option<Pubkey>, // MPC request currently holding the tally
+    pub bump: u8,
+}
+
+impl ConfidentialProposal {
+    /// Reserve the tally for `request` until its callback or expiry
+    pub fn lock(&mut self, request: Pubkey) -> Result<()> {
+        require!(self.pending_request.is_none(), ErrorCode::OperationPending);
+        self.pending_request = Some(request);
+        Ok(())
+    }
+
+    pub fn unlock(&mut self, request: Pubkey) {
+        if self.pending_request == Some(request) {
+            self.pending_request = None;
+        }
+    }
+
+    /// Append the pool total snapshot as an `Enc<Mxe, u64>` circuit
+    /// argument, followed by its initialized flag
+    pub fn total_args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.plaintext_u128(self.total_nonce)
+            .encrypted_u64(self.encrypted_total)
+            .plaintext_bool(self.total_initialized)
+    }
+
+    /// Append the stored tally as an `Enc<Mxe, VoteTally>` circuit argument,
+    /// followed by its initialized flag
+    pub fn tally_args(&self, args: ArgBuilder) -> ArgBuilder {
+        args.plaintext_u128(self.tally_nonce)
+            .encrypted_u64(self.encrypted_tally[0])
+            .encrypted_u64(self.encrypted_tally[1])
+            .plaintext_bool(self.tally_initialized)
+    }
+
+    /// Write back a tally re-encrypted by the MPC
+    pub fn store_tally(&mut self, tally: &MXEEncryptedStruct<2>) {
+        self.tally_nonce = tally.nonce;
+        self.encrypted_tally = tally.ciphertexts;
+        self.tally_initialized = true;
+    }
+}
+
+/// Marks that a member has voted on a confidential proposal; the vote
+/// itself only exists inside the encrypted tally
+#[account]
+pub struct ConfidentialVoteRecord {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct MpcRequest {
+    pub requester: Pubkey,
+    pub target_account: Pubkey, // Account whose ciphertext the computation rewrites
+    pub pool: Pubkey,           // Pool whose total the computation holds, default otherwise
+    pub circuit: Circuit,
+    pub status: MpcRequestStatus,
+    pub created_slot: u64,
+    pub bump: u8,
+}
+
+impl MpcRequest {
+    /// Record a computation that has just been queued
+    pub fn open(
+        &mut self,
+        requester: Pubkey,
+        target_account: Pubkey,
+        pool: Pubkey,
+        circuit: Circuit,
+        bump: u8,
+    ) -> Result<()> {
+        self.requester = requester;
+        self.target_account = target_account;
+        self.pool = pool;
+        self.circuit = circuit;
+        self.status = MpcRequestStatus::Pending;
+        self.created_slot = Clock::get()?.slot;
+        self.bump = bump;
+        Ok(())
+    }
+
+    /// Move a pending request to its final status. Only happens once, so a
+    /// second callback or expiry for the same request is rejected.
+    pub fn settle(&mut self, status: MpcRequestStatus) -> Result<()> {
+        require!(
+            self.status == MpcRequestStatus::Pending,
+            ErrorCode::MpcRequestNotPending
+        );
+        self.status = status;
+        Ok(())
+    }
+}
+
+/// Per-requester sequence number for `MpcRequest` seeds, so requests never
+/// collide and clients can derive the next address without the slot
+#[account]
+pub struct MpcRequestCounter {
+    pub next_request: u64,
+    pub bump: u8,
+}
+
+impl MpcRequestCounter {
+    pub fn advance(&mut self, bump: u8) {
+        self.next_request += 1;
+        self.bump = bump;
+    }
+}
+
+/// Which computation definitions have been initialized and finalized
+#[account]
+pub struct CompDefRegistry {
+    pub initialized: u32, // One bit per `Circuit`
+    pub bump: u8,
+}
+
+impl CompDefRegistry {
+    pub fn is_registered(&self, circuit: Circuit) -> bool {
+        self.initialized & circuit.bit() != 0
+    }
+
+    pub fn register(&mut self, circuit: Circuit, bump: u8) {
+        self.initialized |= circuit.bit();
+        self.bump = bump;
+        msg!("Computation definition initialized: {:?}", circuit);
+    }
+}
+
+// ORACLE AUTHORIZATION
+
+/// Prefix on every oracle-signed message, so the oracle key can't be tricked
+/// into producing a signature that is valid here for some other purpose
+pub const ORACLE_DOMAIN: [u8; 16] = *b"solcircle:oracle";
+
+/// What the oracle signs to approve a fund movement. The program rebuilds
+/// this from the instruction's accounts and arguments, and the Ed25519
+/// instruction must cover its exact Borsh encoding.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct OracleAuthorization {
+    pub domain: [u8; 16],
+    pub program_id: Pubkey,
+    pub cluster: OracleCluster,
+    pub action: OracleAction,
+    pub user: Pubkey,
+    pub group: Pubkey, // Pool account, or default for user-only actions
+    pub mint: Pubkey,
+    pub amount: OracleAmount,
+    pub nonce: u64,
+    pub expiry_slot: u64,
+}
+
+impl OracleAuthorization {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        oracle_config: &OracleConfig,
+        action: OracleAction,
+        user: Pubkey,
+        group: Pubkey,
+        mint: Pubkey,
+        amount: OracleAmount,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Self {
+        Self {
+            domain: ORACLE_DOMAIN,
+            program_id: crate::ID,
+            cluster: oracle_config.cluster,
+            action,
+            user,
+            group,
+            mint,
+            amount,
+            nonce,
+            expiry_slot,
+        }
+    }
+}
+
+/// A timelocked change to the oracle signer set
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum OracleChange {
+    Add(Pubkey),
+    Remove(Pubkey),
+    SetThreshold(u8),
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OracleCluster {
+    Localnet,
+    Devnet,
+    Mainnet,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OracleAction {
+    Withdraw,
+    ReleaseToGroup,
+    ReleaseFromGroup,
+    ConfidentialWithdraw,
+    ConfidentialReleaseToGroup,
+    ConfidentialReleaseFromGroup,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum OracleAmount {
+    Plain(u64),
+    Commitment([u8; 32]), // Hash of an encrypted amount
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ProposalAction {
+    Buy,
+    Sell,
+    Release,  // Pay `amount` from the pool to `recipient`
+    WriteOff, // Stop counting the pool's holdings of `token`
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ProposalStatus {
+    Voting,
+    Passed,
+    Rejected,
+    Executed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum SessionStatus {
+    Open,     // Accepting releases until `start_slot`
+    Locked,   // Funds committed, waiting for trading to start
+    Trading,
+    Settling, // Positions being unwound after `end_slot`
+    Closed,   // Funds claimable
+}
+
+/// `MpcRequest` as older program versions wrote it, seeded by
+/// `[b"mpc_request", requester, created_slot]` and without the `pool` and
+/// `circuit` fields. The account name, and so its discriminator, is the same.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyMpcRequest {
+    pub requester: Pubkey,
+    pub target_account: Pubkey,
+    pub status: MpcRequestStatus,
+    pub created_slot: u64,
     pub bump: u8,
 }
 
-#[account]
-pub struct MpcRequest {
-    pub requester: Pubkey,
-    pub target_account: Pubkey,
-    pub status: MpcRequestStatus,
-    pub created_slot: u64,
-    pub bump: u8,
+impl LegacyMpcRequest {
+    pub const LEN: usize = 8 + 32 + 32 + 1 + 8 + 1;
+
+    /// Decode a request in the old layout, checking it is one of ours
+    pub fn try_from_account(info: &AccountInfo) -> Result<Self> {
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::MpcRequestMismatch);
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() == Self::LEN && data.starts_with(MpcRequest::DISCRIMINATOR),
+            ErrorCode::MpcRequestMismatch
+        );
+        let request = Self::deserialize(&mut &data[8..])?;
+
+        let address = Pubkey::create_program_address(
+            &[
+                b"mpc_request",
+                request.requester.as_ref(),
+                &request.created_slot.to_le_bytes(),
+                &[request.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::MpcRequestMismatch)?;
+        require_keys_eq!(address, info.key(), ErrorCode::MpcRequestMismatch);
+        Ok(request)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub enum MpcRequestStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// Encrypted instructions this program queues, as tracked by `CompDefRegistry`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Circuit {
+    AddToBalance,
+    SubtractFromBalance,
+    ConfidentialTransferToPool,
+    ConfidentialTransferFromPool,
+    VerifyMinimumBalance,
+    ComputeUserShare,
+    ComputeAggregateStats,
+    AddWeightedVote,
+    TallyVotes,
+}
+
+impl Circuit {
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Whether queuing this circuit locks the target confidential deposit
+    pub fn locks_deposit(self) -> bool {
+        matches!(
+            self,
+            Circuit::AddToBalance
+                | Circuit::SubtractFromBalance
+                | Circuit::ConfidentialTransferToPool
+                | Circuit::ConfidentialTransferFromPool
+                | Circuit::ComputeUserShare
+        )
+    }
+
+    /// Whether a failed request of this circuit leaves tokens in escrow
+    pub fn escrows_deposit(self) -> bool {
+        self == Circuit::AddToBalance
+    }
+
+    /// Whether queuing this circuit locks the pool's encrypted total
+    pub fn locks_pool(self) -> bool {
+        matches!(
+            self,
+            Circuit::ConfidentialTransferToPool
+                | Circuit::ConfidentialTransferFromPool
+                | Circuit::ComputeUserShare
+                | Circuit::ComputeAggregateStats
+        )
+    }
+
+    /// Whether queuing this circuit locks a confidential proposal's tally
+    pub fn locks_proposal(self) -> bool {
+        matches!(self, Circuit::AddWeightedVote | Circuit::TallyVotes)
+    }
+
+    /// Whether queuing this circuit locks a member's encrypted contribution
+    pub fn locks_member(self) -> bool {
+        matches!(
+            self,
+            Circuit::ConfidentialTransferToPool
+                | Circuit::ConfidentialTransferFromPool
+                | Circuit::ComputeUserShare
+                | Circuit::AddWeightedVote
+        )
+    }
+}
+
+// ========================================================================
+// CONTEXT STRUCTURES - PUBLIC
+// ========================================================================
+
+#[derive(Accounts)]
+pub struct InitializeOracle<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + (4 + 32 * MAX_ORACLES) + 1 + (1 + 1 + 32) + 8 + (1 + 32) + 8 + 1 + 8 + 1,
+        seeds = [b"oracle_config"],
+        bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Oracle's public key
+    pub oracle_pubkey: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracle<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump,
+        constraint = oracle_config.pending_authority == Some(new_authority.key())
+            @ ErrorCode::Unauthorized
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserDeposit<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 8 + 8 + 1 + 1,
+        seeds = [b"user_deposit", user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"user_vault", user.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = user_vault,
+        token::token_program = token_program
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToUserPda<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == user_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Owner check in account constraint
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(address = user_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromUserPda<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump,
+        has_one = owner @ ErrorCode::Unauthorized
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == user_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Owner check in account constraint
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(address = user_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSolToUserPda<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        constraint = user_deposit.mint == native_mint::ID @ ErrorCode::MintMismatch
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Owner check in account constraint
+    pub owner: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSolFromUserPda<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        constraint = user_deposit.mint == native_mint::ID @ ErrorCode::MintMismatch
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the wSOL and is closed to the user in the same instruction
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"sol_unwrap", user.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = unwrap_account,
+        token::token_program = token_program
+    )]
+    pub unwrap_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = native_mint::ID @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Owner check in account constraint
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: String)]
+pub struct CreateGroupPool<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 68 + 68 + 8 + 8 + 8 + 1 + (4 + 32 * MAX_POOL_POSITIONS) + 1 + 1,
+        seeds = [b"group_pool", group_id.as_bytes(), mint.key().as_ref()],
+        bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"group_vault", group_id.as_bytes(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = group_vault,
+        token::token_program = token_program
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFundsToGroup<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump,
+        constraint = group_pool.mint == user_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"group_vault", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 1,
+        seeds = [b"member_position", group_pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    /// CHECK: User whose funds are being moved
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(address = user_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFundsFromGroup<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_deposit", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", user.key().as_ref(), user_deposit.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump,
+        constraint = group_pool.mint == user_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"group_vault", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [b"member_position", group_pool.key().as_ref(), user.key().as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    /// CHECK: User receiving funds
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(address = user_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CreateProposal<'info> {
+    #[account(
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        seeds = [b"member_position", group_pool.key().as_ref(), proposer.key().as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + 32 + 8 + 32 + 1 + 32 + 8 + 32 + 8 + 8 + 2 + 8 + 8 + 8 + 1 + 1,
+        seeds = [b"proposal", group_pool.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CastVote<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.pool.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        seeds = [b"member_position", proposal.pool.as_ref(), voter.key().as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + 32 + 32 + 1 + 8 + 1,
+        seeds = [b"vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.pool.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteReleaseProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", group_pool.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Passed @ ErrorCode::ProposalNotPassed,
+        constraint = proposal.action == ProposalAction::Release @ ErrorCode::ProposalActionMismatch
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"group_vault", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [b"user_deposit", proposal.recipient.as_ref(), group_pool.mint.as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", proposal.recipient.as_ref(), group_pool.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"member_position", group_pool.key().as_ref(), proposal.recipient.as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    #[account(address = group_pool.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSwapAllowlist<'info> {
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + (4 + 32 * MAX_SWAP_PROGRAMS) + 1,
+        seeds = [b"swap_allowlist"],
+        bump
+    )]
+    pub swap_allowlist: Account<'info, SwapAllowlist>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePoolSwap<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", group_pool.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Passed @ ErrorCode::ProposalNotPassed,
+        constraint = matches!(proposal.action, ProposalAction::Buy | ProposalAction::Sell) @ ErrorCode::ProposalActionMismatch
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"group_vault", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Pool's holdings of `proposal.token`, owned by the group vault
+    #[account(
+        init_if_needed,
+        payer = admin,
+        seeds = [b"pool_token_vault", group_pool.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = group_vault,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [b"swap_allowlist"],
+        bump = swap_allowlist.bump
+    )]
+    pub swap_allowlist: Account<'info, SwapAllowlist>,
+
+    /// CHECK: DEX program, checked against the allowlist
+    #[account(
+        executable,
+        constraint = swap_allowlist.programs.contains(&swap_program.key()) @ ErrorCode::SwapProgramNotAllowed
+    )]
+    pub swap_program: UncheckedAccount<'info>,
+
+    #[account(
+        address = proposal.token @ ErrorCode::MintMismatch,
+        constraint = token_mint.key() != group_pool.mint @ ErrorCode::MintMismatch
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WriteOffPosition<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", group_pool.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Passed @ ErrorCode::ProposalNotPassed,
+        constraint = proposal.action == ProposalAction::WriteOff @ ErrorCode::ProposalActionMismatch
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StartSession<'info> {
+    #[account(
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 32 + 8 + 8 + 8 + 1 + 1,
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceSession<'info> {
+    #[account(
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSessionFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"group_vault", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"session", group_pool.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [b"member_position", group_pool.key().as_ref(), member_position.member.as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+
+    #[account(
+        mut,
+        seeds = [b"user_deposit", member_position.member.as_ref(), group_pool.mint.as_ref()],
+        bump = user_deposit.bump
+    )]
+    pub user_deposit: Account<'info, UserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"user_vault", member_position.member.as_ref(), group_pool.mint.as_ref()],
+        bump = user_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = group_pool.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct GetMemberPosition<'info> {
+    #[account(
+        seeds = [b"group_pool", group_pool.group_id.as_bytes(), group_pool.mint.as_ref()],
+        bump = group_pool.bump
+    )]
+    pub group_pool: Account<'info, GroupPool>,
+
+    #[account(
+        seeds = [b"member_position", group_pool.key().as_ref(), member_position.member.as_ref()],
+        bump = member_position.bump
+    )]
+    pub member_position: Account<'info, MemberPosition>,
+}
+
+// ========================================================================
+// CONTEXT STRUCTURES - COMPUTATION DEFINITIONS
+// ========================================================================
+
+#[init_computation_definition_accounts("add_to_balance", payer)]
+#[derive(Accounts)]
+pub struct InitAddToBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("subtract_from_balance", payer)]
+#[derive(Accounts)]
+pub struct InitSubtractFromBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("confidential_transfer_to_pool", payer)]
+#[derive(Accounts)]
+pub struct InitConfidentialTransferToPoolCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("confidential_transfer_from_pool", payer)]
+#[derive(Accounts)]
+pub struct InitConfidentialTransferFromPoolCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("verify_minimum_balance", payer)]
+#[derive(Accounts)]
+pub struct InitVerifyMinimumBalanceCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("compute_user_share", payer)]
+#[derive(Accounts)]
+pub struct InitComputeUserShareCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("compute_aggregate_stats", payer)]
+#[derive(Accounts)]
+pub struct InitComputeAggregateStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("add_weighted_vote", payer)]
+#[derive(Accounts)]
+pub struct InitAddWeightedVoteCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("tally_votes", payer)]
+#[derive(Accounts)]
+pub struct InitTallyVotesCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, address = derive_mxe_pda!())]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by the arcium program; not initialized yet
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 4 + 1,
+        seeds = [b"comp_def_registry"],
+        bump
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+// ========================================================================
+// CONTEXT STRUCTURES - CONFIDENTIAL
+// ========================================================================
+
+#[derive(Accounts)]
+pub struct InitializeConfidentialDeposit<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 16 + 32 + 1 + 8 + 1 + 8 + (1 + 32) + 1 + 1,
+        seeds = [b"confidential_deposit", user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"confidential_vault", user.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = user_vault,
+        token::token_program = token_program
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("add_to_balance", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ConfidentialDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", user.key().as_ref()],
+        bump
+    )]
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", user.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    /// Holds the deposit until the callback credits it to the ledger
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"deposit_escrow", mpc_request.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = deposit_escrow,
+        token::token_program = token_program
+    )]
+    pub deposit_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::AddToBalance) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADD_TO_BALANCE))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("add_to_balance", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ConfidentialDepositSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        address = confidential_deposit.mint @ ErrorCode::MintMismatch,
+        constraint = mint.key() == native_mint::ID @ ErrorCode::MintMismatch
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", user.key().as_ref()],
+        bump
+    )]
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", user.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    /// Holds the wrapped lamports until the callback credits them to the ledger
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"deposit_escrow", mpc_request.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = deposit_escrow,
+        token::token_program = token_program
+    )]
+    pub deposit_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::AddToBalance) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADD_TO_BALANCE))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[callback_accounts("add_to_balance")]
+#[derive(Accounts)]
+pub struct AddToBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADD_TO_BALANCE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = mpc_request.circuit == Circuit::AddToBalance @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == confidential_deposit.owner @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_deposit.key() @ ErrorCode::MpcRequestMismatch
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"deposit_escrow", mpc_request.key().as_ref()],
+        bump
+    )]
+    pub deposit_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Deposit owner, gets the escrow's rent back
+    #[account(mut, address = confidential_deposit.owner)]
+    pub user: UncheckedAccount<'info>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RefundConfidentialDeposit<'info> {
+    #[account(
+        mut,
+        constraint = mpc_request.requester == user.key() @ ErrorCode::Unauthorized,
+        constraint = mpc_request.circuit == Circuit::AddToBalance @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.status == MpcRequestStatus::Failed @ ErrorCode::MpcRequestNotFailed
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        mut,
+        seeds = [b"deposit_escrow", mpc_request.key().as_ref()],
+        bump
+    )]
+    pub deposit_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required unless the escrow holds wSOL
+    #[account(
+        mut,
+        token::mint = deposit_escrow.mint,
+        token::authority = user
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(address = deposit_escrow.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
-pub enum MpcRequestStatus {
-    Pending,
-    Completed,
-    Failed,
+#[queue_computation_accounts("subtract_from_balance", user)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ConfidentialWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", user.key().as_ref()],
+        bump
+    )]
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", user.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = user,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::SubtractFromBalance) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBTRACT_FROM_BALANCE))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[callback_accounts("subtract_from_balance")]
+#[derive(Accounts)]
+pub struct SubtractFromBalanceCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_SUBTRACT_FROM_BALANCE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = mpc_request.circuit == Circuit::SubtractFromBalance @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == confidential_deposit.owner @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_deposit.key() @ ErrorCode::MpcRequestMismatch
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Deposit owner, gets the request's rent back
+    #[account(mut, address = confidential_deposit.owner)]
+    pub user: UncheckedAccount<'info>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: String)]
+pub struct CreateConfidentialGroupPool<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 68 + 68 + 16 + 32 + 1 + 16 + 32 + 4 + 1 + 1 + (1 + 32) + 1 + 1,
+        seeds = [b"confidential_pool", group_id.as_bytes(), mint.key().as_ref()],
+        bump
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"confidential_group_vault", group_id.as_bytes(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = group_vault,
+        token::token_program = token_program
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("confidential_transfer_to_pool", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ConfidentialReleaseToGroup<'info> {
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump,
+        constraint = confidential_pool.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 32 + 16 + 32 + 1 + (1 + 32) + 8 + 1,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
+
+    /// CHECK: User whose funds are being moved
+    pub user: UncheckedAccount<'info>,
+
+    /// Relayer submitting the oracle-approved transfer and paying MPC fees
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
+    )]
+    pub oracle_config: Account<'info, OracleConfig>,
+
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", user.key().as_ref()],
+        bump
+    )]
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", user.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::ConfidentialTransferToPool) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_TO_POOL))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
-// ========================================================================
-// CONTEXT STRUCTURES - PUBLIC
-// ========================================================================
+#[callback_accounts("confidential_transfer_to_pool")]
+#[derive(Accounts)]
+pub struct ConfidentialTransferToPoolCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_TO_POOL))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = mpc_request.circuit == Circuit::ConfidentialTransferToPool @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == confidential_deposit.owner @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_deposit.key() @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.pool == confidential_pool.key() @ ErrorCode::MpcRequestMismatch
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
+    )]
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), confidential_deposit.owner.as_ref()],
+        bump = confidential_member.bump
+    )]
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
+
+    /// CHECK: Deposit owner, gets the request's rent back
+    #[account(mut, address = confidential_deposit.owner)]
+    pub user: UncheckedAccount<'info>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
 
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[queue_computation_accounts("confidential_transfer_from_pool", payer)]
 #[derive(Accounts)]
-pub struct InitializeOracle<'info> {
+#[instruction(computation_offset: u64)]
+pub struct ConfidentialReleaseFromGroup<'info> {
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 1,
-        seeds = [b"oracle_config"],
-        bump
+        mut,
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: Oracle's public key
-    pub oracle_pubkey: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump,
+        constraint = confidential_pool.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
-    pub system_program: Program<'info, System>,
-}
+    #[account(
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
 
-#[derive(Accounts)]
-pub struct UpdateOracle<'info> {
     #[account(
         mut,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), user.key().as_ref()],
+        bump = confidential_member.bump
+    )]
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
+
+    /// CHECK: User receiving funds
+    pub user: UncheckedAccount<'info>,
+
+    /// Relayer submitting the oracle-approved transfer and paying MPC fees
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
         seeds = [b"oracle_config"],
-        bump = oracle_config.bump,
-        has_one = authority @ ErrorCode::Unauthorized
+        bump = oracle_config.bump
     )]
     pub oracle_config: Account<'info, OracleConfig>,
 
-    pub authority: Signer<'info>,
-}
+    /// CHECK: Instructions sysvar, read to find the oracle's Ed25519 instruction
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", user.key().as_ref()],
+        bump
+    )]
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
 
-#[derive(Accounts)]
-pub struct InitializeUserDeposit<'info> {
     #[account(
         init,
-        payer = user,
-        space = 8 + 32 + 8 + 1,
-        seeds = [b"user_deposit", user.key().as_ref()],
+        payer = payer,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", user.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
         bump
     )]
-    pub user_deposit: Account<'info, UserDeposit>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
 
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::ConfidentialTransferFromPool) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_FROM_POOL))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("confidential_transfer_from_pool")]
 #[derive(Accounts)]
-pub struct DepositToUserPda<'info> {
+pub struct ConfidentialTransferFromPoolCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_CONFIDENTIAL_TRANSFER_FROM_POOL))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
     #[account(
         mut,
-        seeds = [b"user_deposit", user.key().as_ref()],
-        bump = user_deposit.bump,
-        has_one = owner @ ErrorCode::Unauthorized
+        constraint = mpc_request.circuit == Circuit::ConfidentialTransferFromPool @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == confidential_deposit.owner @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_deposit.key() @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.pool == confidential_pool.key() @ ErrorCode::MpcRequestMismatch
     )]
-    pub user_deposit: Account<'info, UserDeposit>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
     #[account(
         mut,
-        seeds = [b"user_vault", user.key().as_ref()],
-        bump
+        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
+    )]
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
-    /// CHECK: Owner check in account constraint
-    pub owner: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), confidential_deposit.owner.as_ref()],
+        bump = confidential_member.bump
+    )]
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
+
+    /// CHECK: Deposit owner, gets the request's rent back
+    #[account(mut, address = confidential_deposit.owner)]
+    pub user: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
+#[queue_computation_accounts("compute_aggregate_stats", admin)]
 #[derive(Accounts)]
-pub struct WithdrawFromUserPda<'info> {
+#[instruction(computation_offset: u64)]
+pub struct ComputePoolStats<'info> {
     #[account(
         mut,
-        seeds = [b"user_deposit", user.key().as_ref()],
-        bump = user_deposit.bump,
-        has_one = owner @ ErrorCode::Unauthorized
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump,
+        has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub user_deposit: Account<'info, UserDeposit>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"user_vault", user.key().as_ref()],
+        init_if_needed,
+        payer = admin,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", admin.key().as_ref()],
         bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", admin.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = admin,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
 
-    /// CHECK: Owner check in account constraint
-    pub owner: UncheckedAccount<'info>,
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
 
     #[account(
-        seeds = [b"oracle_config"],
-        bump = oracle_config.bump
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::ComputeAggregateStats) @ ErrorCode::CircuitNotInitialized
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_AGGREGATE_STATS))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("compute_aggregate_stats")]
 #[derive(Accounts)]
-#[instruction(group_id: String)]
-pub struct CreateGroupPool<'info> {
+pub struct ComputeAggregateStatsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_AGGREGATE_STATS))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
     #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 68 + 68 + 8 + 1 + 1,
-        seeds = [b"group_pool", group_id.as_bytes()],
-        bump
+        mut,
+        constraint = mpc_request.circuit == Circuit::ComputeAggregateStats @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == confidential_pool.admin @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_pool.key() @ ErrorCode::MpcRequestMismatch
     )]
-    pub group_pool: Account<'info, GroupPool>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
+    )]
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
-    pub system_program: Program<'info, System>,
+    /// CHECK: Pool admin, gets the request's rent back
+    #[account(mut, address = confidential_pool.admin)]
+    pub admin: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ReleaseFundsToGroup<'info> {
+pub struct CloseConfidentialPool<'info> {
     #[account(
         mut,
-        seeds = [b"user_deposit", user.key().as_ref()],
-        bump = user_deposit.bump
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump,
+        has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub user_deposit: Account<'info, UserDeposit>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+
+    pub admin: Signer<'info>,
+}
 
+#[queue_computation_accounts("compute_user_share", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SettleConfidentialMember<'info> {
     #[account(
         mut,
-        seeds = [b"user_vault", user.key().as_ref()],
-        bump
+        seeds = [b"confidential_deposit", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
 
     #[account(
         mut,
-        seeds = [b"group_pool", group_pool.group_id.as_bytes()],
-        bump = group_pool.bump
+        seeds = [b"confidential_vault", user.key().as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
     )]
-    pub group_pool: Account<'info, GroupPool>,
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"group_vault", group_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump,
+        constraint = confidential_pool.mint == confidential_deposit.mint @ ErrorCode::MintMismatch
     )]
-    pub group_vault: Account<'info, TokenAccount>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
-    /// CHECK: User whose funds are being moved
+    #[account(
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
+    )]
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), user.key().as_ref()],
+        bump = confidential_member.bump
+    )]
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
+
+    /// CHECK: Member being settled, only used to derive their accounts
     pub user: UncheckedAccount<'info>,
 
+    /// Anyone cranking the settlement; pays MPC fees and gets the request's rent back
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
     #[account(
-        seeds = [b"oracle_config"],
-        bump = oracle_config.bump
+        init_if_needed,
+        payer = payer,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", payer.key().as_ref()],
+        bump
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", payer.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
+        bump
+    )]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::ComputeUserShare) @ ErrorCode::CircuitNotInitialized
+    )]
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_USER_SHARE))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("compute_user_share")]
 #[derive(Accounts)]
-pub struct ReleaseFundsFromGroup<'info> {
+pub struct ComputeUserShareCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_USER_SHARE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
     #[account(
         mut,
-        seeds = [b"user_deposit", user.key().as_ref()],
-        bump = user_deposit.bump
+        constraint = mpc_request.circuit == Circuit::ComputeUserShare @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == confidential_deposit.key() @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.pool == confidential_pool.key() @ ErrorCode::MpcRequestMismatch
     )]
-    pub user_deposit: Account<'info, UserDeposit>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
     #[account(
         mut,
-        seeds = [b"user_vault", user.key().as_ref()],
-        bump
+        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
 
     #[account(
         mut,
-        seeds = [b"group_pool", group_pool.group_id.as_bytes()],
-        bump = group_pool.bump
+        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref(), confidential_deposit.mint.as_ref()],
+        bump = confidential_deposit.vault_bump
     )]
-    pub group_pool: Account<'info, GroupPool>,
+    pub user_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"group_vault", group_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
     )]
-    pub group_vault: Account<'info, TokenAccount>,
-
-    /// CHECK: User receiving funds
-    pub user: UncheckedAccount<'info>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
     #[account(
-        seeds = [b"oracle_config"],
-        bump = oracle_config.bump
+        mut,
+        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.vault_bump
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
-
-    pub token_program: Program<'info, Token>,
-}
+    pub group_vault: InterfaceAccount<'info, TokenAccount>,
 
-// ========================================================================
-// CONTEXT STRUCTURES - CONFIDENTIAL
-// ========================================================================
-
-#[derive(Accounts)]
-pub struct InitializeConfidentialDeposit<'info> {
     #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 8 + 1 + 1,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), confidential_deposit.owner.as_ref()],
+        bump = confidential_member.bump
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// CHECK: Whoever cranked the settlement, gets the request's rent back
+    #[account(mut, address = mpc_request.requester)]
+    pub requester: UncheckedAccount<'info>,
 
-    pub system_program: Program<'info, System>,
+    #[account(address = confidential_deposit.mint @ ErrorCode::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct ConfidentialDeposit<'info> {
+#[instruction(proposal_id: u64)]
+pub struct CreateConfidentialProposal<'info> {
     #[account(
-        mut,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump = confidential_deposit.bump
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_vault", user.key().as_ref()],
-        bump
+        seeds = [b"confidential_member", confidential_pool.key().as_ref(), proposer.key().as_ref()],
+        bump = confidential_member.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
 
     #[account(
         init,
-        payer = user,
-        space = 8 + 32 + 32 + 1 + 8 + 1,
-        seeds = [b"mpc_request", user.key().as_ref(), &Clock::get()?.slot.to_le_bytes()],
+        payer = proposer,
+        space = 8 + 32 + 8 + 32 + 1 + 32 + 8 + 8 + 8 + 2 + 16 + 32 + 1 + 16 + 64 + 1 + 4 + 2 + 1 + (1 + 32) + 1,
+        seeds = [b"confidential_proposal", confidential_pool.key().as_ref(), &proposal_id.to_le_bytes()],
         bump
     )]
-    pub mpc_request: Account<'info, MpcRequest>,
+    pub proposal: Account<'info, ConfidentialProposal>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+#[queue_computation_accounts("add_weighted_vote", voter)]
 #[derive(Accounts)]
-pub struct ConfidentialDepositCallback<'info> {
+#[instruction(computation_offset: u64)]
+pub struct CastConfidentialVote<'info> {
     #[account(
         mut,
-        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref()],
-        bump = confidential_deposit.bump
+        seeds = [b"confidential_proposal", proposal.pool.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
-
-    /// CHECK: Must be Arcium MPC authority
-    pub mpc_signer: Signer<'info>,
-
-    #[account(mut)]
-    pub mpc_request: Account<'info, MpcRequest>,
-}
+    pub proposal: Account<'info, ConfidentialProposal>,
 
-#[derive(Accounts)]
-pub struct ConfidentialWithdraw<'info> {
     #[account(
         mut,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump = confidential_deposit.bump
+        seeds = [b"confidential_member", proposal.pool.as_ref(), voter.key().as_ref()],
+        bump = confidential_member.bump
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
 
+    /// One vote per member; `init` fails if this member already voted
     #[account(
-        mut,
-        seeds = [b"confidential_vault", user.key().as_ref()],
+        init,
+        payer = voter,
+        space = 8 + 32 + 32 + 1,
+        seeds = [b"confidential_vote", proposal.key().as_ref(), voter.key().as_ref()],
         bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub vote_record: Account<'info, ConfidentialVoteRecord>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub voter: Signer<'info>,
 
     #[account(
-        seeds = [b"oracle_config"],
-        bump = oracle_config.bump
+        init_if_needed,
+        payer = voter,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", voter.key().as_ref()],
+        bump
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
 
     #[account(
         init,
-        payer = user,
-        space = 8 + 32 + 32 + 1 + 8 + 1,
-        seeds = [b"mpc_request", user.key().as_ref(), &Clock::get()?.slot.to_le_bytes()],
+        payer = voter,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", voter.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
         bump
     )]
     pub mpc_request: Account<'info, MpcRequest>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct ConfidentialWithdrawCallback<'info> {
     #[account(
-        mut,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump = confidential_deposit.bump
+        init_if_needed,
+        space = 9,
+        payer = voter,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_vault", user.key().as_ref()],
-        bump
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::AddWeightedVote) @ ErrorCode::CircuitNotInitialized
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
 
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADD_WEIGHTED_VOTE))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
 
-    /// CHECK: User receiving withdrawal
-    pub user: UncheckedAccount<'info>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
 
-    /// CHECK: Must be Arcium MPC authority
-    pub mpc_signer: Signer<'info>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
 
-    #[account(mut)]
-    pub mpc_request: Account<'info, MpcRequest>,
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("add_weighted_vote")]
 #[derive(Accounts)]
-#[instruction(group_id: String)]
-pub struct CreateConfidentialGroupPool<'info> {
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 68 + 68 + 32 + 4 + 1 + 1 + 1,
-        seeds = [b"confidential_pool", group_id.as_bytes()],
-        bump
-    )]
-    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+pub struct AddWeightedVoteCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
 
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_ADD_WEIGHTED_VOTE))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
 
-    pub system_program: Program<'info, System>,
-}
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
 
-#[derive(Accounts)]
-pub struct ConfidentialReleaseToGroup<'info> {
     #[account(
         mut,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump = confidential_deposit.bump
+        constraint = mpc_request.circuit == Circuit::AddWeightedVote @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.requester == voter.key() @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == proposal.key() @ ErrorCode::MpcRequestMismatch
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
     #[account(
         mut,
-        seeds = [b"confidential_vault", user.key().as_ref()],
-        bump
+        seeds = [b"confidential_proposal", proposal.pool.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub proposal: Account<'info, ConfidentialProposal>,
 
     #[account(
         mut,
-        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes()],
-        bump = confidential_pool.bump
+        seeds = [b"confidential_vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump = vote_record.bump
     )]
-    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+    pub vote_record: Account<'info, ConfidentialVoteRecord>,
 
     #[account(
         mut,
-        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"confidential_member", proposal.pool.as_ref(), voter.key().as_ref()],
+        bump = confidential_member.bump
     )]
-    pub group_vault: Account<'info, TokenAccount>,
+    pub confidential_member: Account<'info, ConfidentialMemberPosition>,
 
-    /// CHECK: User whose funds are being moved
-    pub user: UncheckedAccount<'info>,
+    /// CHECK: Voter, gets the request's rent back
+    #[account(mut, address = vote_record.voter)]
+    pub voter: UncheckedAccount<'info>,
+}
 
+#[queue_computation_accounts("tally_votes", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct TallyConfidentialVotes<'info> {
     #[account(
-        seeds = [b"oracle_config"],
-        bump = oracle_config.bump
+        mut,
+        seeds = [b"confidential_proposal", confidential_pool.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub oracle_config: Account<'info, OracleConfig>,
+    pub proposal: Account<'info, ConfidentialProposal>,
 
     #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 1 + 8 + 1,
-        seeds = [b"mpc_request", user.key().as_ref(), &Clock::get()?.slot.to_le_bytes()],
-        bump
+        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes(), confidential_pool.mint.as_ref()],
+        bump = confidential_pool.bump
     )]
-    pub mpc_request: Account<'info, MpcRequest>,
+    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+    /// Anyone can close out a vote once the deadline has passed
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-#[derive(Accounts)]
-pub struct ConfidentialTransferCallback<'info> {
     #[account(
-        mut,
-        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref()],
-        bump = confidential_deposit.bump
+        init_if_needed,
+        payer = payer,
+        space = 8 + 8 + 1,
+        seeds = [b"mpc_request_counter", payer.key().as_ref()],
+        bump
     )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    pub mpc_request_counter: Account<'info, MpcRequestCounter>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref()],
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 32 + 1 + 1 + 8 + 1,
+        seeds = [b"mpc_request", payer.key().as_ref(), &mpc_request_counter.next_request.to_le_bytes()],
         bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes()],
-        bump = confidential_pool.bump
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
     )]
-    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(address = derive_mxe_pda!())]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(mut, address = derive_mempool_pda!())]
+    /// CHECK: mempool_account, checked by the arcium program
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_execpool_pda!())]
+    /// CHECK: executing_pool, checked by the arcium program
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(mut, address = derive_comp_pda!(computation_offset))]
+    /// CHECK: computation_account, checked by the arcium program
+    pub computation_account: UncheckedAccount<'info>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"comp_def_registry"],
+        bump = comp_def_registry.bump,
+        constraint = comp_def_registry.is_registered(Circuit::TallyVotes) @ ErrorCode::CircuitNotInitialized
     )]
-    pub group_vault: Account<'info, TokenAccount>,
+    pub comp_def_registry: Account<'info, CompDefRegistry>,
 
-    /// CHECK: User whose funds were moved
-    pub user: UncheckedAccount<'info>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TALLY_VOTES))]
+    /// CHECK: comp_def_account, checked by the arcium program once the
+    /// registry confirms it is initialized
+    pub comp_def_account: UncheckedAccount<'info>,
 
-    /// CHECK: Must be Arcium MPC authority
-    pub mpc_signer: Signer<'info>,
+    #[account(mut, address = derive_cluster_pda!(mxe_account))]
+    pub cluster_account: Account<'info, Cluster>,
 
-    #[account(mut)]
-    pub mpc_request: Account<'info, MpcRequest>,
+    #[account(mut, address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS)]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(address = ARCIUM_CLOCK_ACCOUNT_ADDRESS)]
+    pub clock_account: Account<'info, ClockAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
 }
 
+#[callback_accounts("tally_votes")]
 #[derive(Accounts)]
-pub struct ConfidentialReleaseFromGroup<'info> {
-    #[account(
-        mut,
-        seeds = [b"confidential_deposit", user.key().as_ref()],
-        bump = confidential_deposit.bump
-    )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+pub struct TallyVotesCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
 
-    #[account(
-        mut,
-        seeds = [b"confidential_vault", user.key().as_ref()],
-        bump
-    )]
-    pub user_vault: Account<'info, TokenAccount>,
+    #[account(address = derive_comp_def_pda!(COMP_DEF_OFFSET_TALLY_VOTES))]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    /// CHECK: Instructions sysvar, checked by the account constraint
+    #[account(address = ix_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
 
     #[account(
         mut,
-        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes()],
-        bump = confidential_pool.bump
+        constraint = mpc_request.circuit == Circuit::TallyVotes @ ErrorCode::MpcRequestMismatch,
+        constraint = mpc_request.target_account == proposal.key() @ ErrorCode::MpcRequestMismatch
     )]
-    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+    pub mpc_request: Account<'info, MpcRequest>,
 
     #[account(
         mut,
-        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"confidential_proposal", proposal.pool.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub group_vault: Account<'info, TokenAccount>,
+    pub proposal: Account<'info, ConfidentialProposal>,
 
-    /// CHECK: User receiving funds
-    pub user: UncheckedAccount<'info>,
+    /// CHECK: Caller who queued the tally, gets the request's rent back
+    #[account(mut, address = mpc_request.requester)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireMpcRequest<'info> {
+    #[account(mut)]
+    pub mpc_request: Account<'info, MpcRequest>,
+
+    /// CHECK: Gets the request's rent back
+    #[account(mut, address = mpc_request.requester @ ErrorCode::MpcRequestMismatch)]
+    pub requester: UncheckedAccount<'info>,
 
     #[account(
         seeds = [b"oracle_config"],
@@ -1290,59 +6408,151 @@ pub struct ConfidentialReleaseFromGroup<'info> {
     )]
     pub oracle_config: Account<'info, OracleConfig>,
 
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 1 + 8 + 1,
-        seeds = [b"mpc_request", user.key().as_ref(), &Clock::get()?.slot.to_le_bytes()],
-        bump
-    )]
-    pub mpc_request: Account<'info, MpcRequest>,
+    /// Required when the request's circuit locks a deposit
+    #[account(mut, address = mpc_request.target_account @ ErrorCode::MpcRequestMismatch)]
+    pub confidential_deposit: Option<Account<'info, ConfidentialUserDeposit>>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+    /// Required when the request's circuit locks a pool
+    #[account(mut, address = mpc_request.pool @ ErrorCode::MpcRequestMismatch)]
+    pub confidential_pool: Option<Account<'info, ConfidentialGroupPool>>,
 
-#[derive(Accounts)]
-pub struct ConfidentialTransferFromPoolCallback<'info> {
-    #[account(
-        mut,
-        seeds = [b"confidential_deposit", confidential_deposit.owner.as_ref()],
-        bump = confidential_deposit.bump
-    )]
-    pub confidential_deposit: Account<'info, ConfidentialUserDeposit>,
+    /// Required when the request's circuit locks a proposal's tally
+    #[account(mut, address = mpc_request.target_account @ ErrorCode::MpcRequestMismatch)]
+    pub confidential_proposal: Option<Account<'info, ConfidentialProposal>>,
 
+    /// Required when the request's circuit locks a member's contribution
     #[account(
         mut,
-        seeds = [b"confidential_vault", confidential_deposit.owner.as_ref()],
-        bump
+        seeds = [b"confidential_member", mpc_request.pool.as_ref(), confidential_member.member.as_ref()],
+        bump = confidential_member.bump
     )]
-    pub user_vault: Account<'info, TokenAccount>,
+    pub confidential_member: Option<Account<'info, ConfidentialMemberPosition>>,
 
+    /// Required for an expired `AddWeightedVote`, whose vote never counted
     #[account(
         mut,
-        seeds = [b"confidential_pool", confidential_pool.group_id.as_bytes()],
-        bump = confidential_pool.bump
+        seeds = [b"confidential_vote", mpc_request.target_account.as_ref(), mpc_request.requester.as_ref()],
+        bump = vote_record.bump
     )]
-    pub confidential_pool: Account<'info, ConfidentialGroupPool>,
+    pub vote_record: Option<Account<'info, ConfidentialVoteRecord>>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimMpcRequests<'info> {
+    #[account(mut)]
+    pub requester: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"confidential_group_vault", confidential_pool.group_id.as_bytes()],
-        bump
+        seeds = [b"oracle_config"],
+        bump = oracle_config.bump
     )]
-    pub group_vault: Account<'info, TokenAccount>,
+    pub oracle_config: Account<'info, OracleConfig>,
+}
 
-    /// CHECK: User receiving funds
-    pub user: UncheckedAccount<'info>,
+// ========================================================================
+// EVENTS
+// ========================================================================
+
+/// Emitted whenever an MPC callback writes a new encrypted user balance
+#[event]
+pub struct ConfidentialBalanceUpdated {
+    pub owner: Pubkey,
+    pub slot: u64,
+}
+
+/// Emitted when a computation fails inside the MPC or expires without a callback
+#[event]
+pub struct MpcRequestFailed {
+    pub request: Pubkey,
+    pub requester: Pubkey,
+    pub expired: bool,
+}
 
-    /// CHECK: Must be Arcium MPC authority
-    pub mpc_signer: Signer<'info>,
+/// Emitted when a public proposal's voting period is closed out
+#[event]
+pub struct ProposalFinalized {
+    pub proposal: Pubkey,
+    pub status: ProposalStatus,
+    pub yes_weight: u64,
+    pub no_weight: u64,
+}
 
-    #[account(mut)]
-    pub mpc_request: Account<'info, MpcRequest>,
+/// Emitted whenever a trading session changes state
+#[event]
+pub struct SessionAdvanced {
+    pub session: Pubkey,
+    pub session_id: u64,
+    pub status: SessionStatus,
+}
+
+/// Emitted when the MPC reveals a confidential proposal's outcome
+#[event]
+pub struct ConfidentialProposalTallied {
+    pub proposal: Pubkey,
+    pub status: ProposalStatus,
+    pub turnout_bps: u16,
+}
+
+/// Emitted when a pool trades for a `Buy` or `Sell` proposal
+#[event]
+pub struct PoolSwapExecuted {
+    pub pool: Pubkey,
+    pub proposal: Pubkey,
+    pub swap_program: Pubkey,
+    pub action: ProposalAction,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// Emitted when a dark pool member is paid out of a closed pool. The amount
+/// is public anyway, as it leaves the vault in a plain token transfer.
+#[event]
+pub struct ConfidentialMemberSettled {
+    pub pool: Pubkey,
+    pub member: Pubkey,
+    pub amount: u64,
+}
+
+/// Emitted when the MPC reports a dark pool's aggregate health
+#[event]
+pub struct PoolStatsUpdated {
+    pub pool: Pubkey,
+    pub participant_count: u32,
+    pub has_minimum_participants: bool,
+    pub pool_is_healthy: bool,
+}
+
+#[event]
+pub struct OracleProposed {
+    pub change: OracleChange,
+    pub activation_slot: u64,
+}
+
+#[event]
+pub struct OracleAccepted {
+    pub change: OracleChange,
+}
+
+#[event]
+pub struct OracleProposalCancelled {
+    pub change: OracleChange,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub new_authority: Pubkey,
+    pub activation_slot: u64,
+}
+
+#[event]
+pub struct AuthorityAccepted {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
 
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct AuthorityTransferCancelled {
+    pub new_authority: Pubkey,
 }
 
 // ========================================================================
@@ -1366,9 +6576,6 @@ pub enum ErrorCode {
     #[msg("Invalid oracle signature.")]
     InvalidOracleSignature,
 
-    #[msg("Invalid MPC signer. Must be Arcium MPC authority.")]
-    InvalidMpcSigner,
-
     #[msg("Invalid encrypted result format.")]
     InvalidEncryptedResult,
 
@@ -1377,4 +6584,160 @@ pub enum ErrorCode {
 
     #[msg("Transfer failed.")]
     TransferFailed,
+
+    #[msg("Oracle nonce has already been used.")]
+    StaleOracleNonce,
+
+    #[msg("Oracle authorization has expired.")]
+    OracleAuthorizationExpired,
+
+    #[msg("Oracle signer set is full.")]
+    TooManyOracles,
+
+    #[msg("Oracle key is already registered.")]
+    OracleAlreadyRegistered,
+
+    #[msg("Oracle key not found.")]
+    OracleNotFound,
+
+    #[msg("Oracle threshold must be between 1 and the number of oracle keys.")]
+    InvalidOracleThreshold,
+
+    #[msg("A change is already pending.")]
+    ProposalAlreadyPending,
+
+    #[msg("No pending change to accept or cancel.")]
+    NoPendingChange,
+
+    #[msg("Timelock has not elapsed yet.")]
+    TimelockNotElapsed,
+
+    #[msg("Computation definition for this circuit has not been initialized.")]
+    CircuitNotInitialized,
+
+    #[msg("MPC request does not match this computation.")]
+    MpcRequestMismatch,
+
+    #[msg("MPC request has already been settled.")]
+    MpcRequestNotPending,
+
+    #[msg("MPC request has not timed out yet.")]
+    MpcRequestNotExpired,
+
+    #[msg("Another confidential operation is still pending on this account.")]
+    OperationPending,
+
+    #[msg("MPC request has not failed, so there is nothing to refund.")]
+    MpcRequestNotFailed,
+
+    #[msg("MPC request is still pending. Wait for its callback or expire it first.")]
+    MpcRequestNotSettled,
+
+    #[msg("Failed deposit still holds escrowed tokens. Refund it instead.")]
+    MpcRequestHasEscrow,
+
+    #[msg("Token account or pool is for a different mint.")]
+    MintMismatch,
+
+    #[msg("A token account is required to receive this mint.")]
+    TokenAccountRequired,
+
+    #[msg("Mint uses a Token-2022 extension this program does not support.")]
+    UnsupportedMintExtension,
+
+    #[msg("Voting period must be at least one slot.")]
+    InvalidVotingPeriod,
+
+    #[msg("Quorum must be between 1 and 10000 basis points.")]
+    InvalidQuorum,
+
+    #[msg("Member has no locked contribution to vote with.")]
+    NoVotingWeight,
+
+    #[msg("Proposal is no longer open for voting.")]
+    ProposalNotVoting,
+
+    #[msg("Voting deadline has passed.")]
+    VotingClosed,
+
+    #[msg("Voting deadline has not passed yet.")]
+    VotingStillOpen,
+
+    #[msg("Proposal has not passed.")]
+    ProposalNotPassed,
+
+    #[msg("Proposal is for a different action.")]
+    ProposalActionMismatch,
+
+    #[msg("Session must start in the future and end after it starts.")]
+    InvalidSessionWindow,
+
+    #[msg("The pool's current session has not closed.")]
+    SessionInProgress,
+
+    #[msg("Session is not accepting releases into the pool.")]
+    SessionNotOpen,
+
+    #[msg("Pool funds are locked until the session closes.")]
+    SessionLocked,
+
+    #[msg("Session can't advance to its next state yet.")]
+    SessionTransitionNotAllowed,
+
+    #[msg("Member has nothing to claim from this pool.")]
+    NothingToClaim,
+
+    #[msg("Amount exceeds the member's entitlement in this pool.")]
+    ExceedsEntitlement,
+
+    #[msg("Deposit is too small to mint any pool shares.")]
+    DepositTooSmall,
+
+    #[msg("Pool has outstanding shares but nothing left in its vault.")]
+    PoolValueLost,
+
+    #[msg("Pool must be closed before its members can settle.")]
+    PoolStillActive,
+
+    #[msg("Swap program is not on the allowlist.")]
+    SwapProgramNotAllowed,
+
+    #[msg("Swap program is already on the allowlist.")]
+    SwapProgramAlreadyAllowed,
+
+    #[msg("Too many swap programs.")]
+    TooManySwapPrograms,
+
+    #[msg("Pool can't make this trade in its current session state.")]
+    SessionNotTrading,
+
+    #[msg("Swap spent more than the proposal allows.")]
+    SwapInputExceeded,
+
+    #[msg("Swap returned less than the minimum amount out.")]
+    SlippageExceeded,
+
+    #[msg("Position was topped up after the proposal opened.")]
+    VotingWeightChanged,
+
+    #[msg("Session must be closed before funds are claimed.")]
+    SessionNotClosed,
+
+    #[msg("Pool holds bought tokens; sell them before members enter or exit.")]
+    PoolHasOpenPositions,
+
+    #[msg("Pool already holds the maximum number of tokens.")]
+    TooManyOpenPositions,
+
+    #[msg("Swap was passed another token account owned by the group vault.")]
+    SwapAccountNotAllowed,
+
+    #[msg("Tally counted more weight than the pool held when voting opened.")]
+    TurnoutExceedsPool,
+
+    #[msg("Pool holds no open position in this token.")]
+    PositionNotOpen,
+
+    #[msg("Swap changed the owner, delegate or close authority of a pool vault.")]
+    SwapVaultAltered,
 }
@@ -0,0 +1,254 @@
+// Helpers shared by the program-test suites
+#![allow(dead_code)]
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use anchor_spl::token::spl_token;
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    ed25519_program,
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solcircle_arcium::{ErrorCode, OracleCluster, OracleConfig};
+
+/// Preloads the accounts a suite starts from, then starts the program
+pub struct SetupBuilder {
+    program_test: ProgramTest,
+    start_slot: Option<u64>,
+}
+
+impl Default for SetupBuilder {
+    fn default() -> Self {
+        Self {
+            program_test: ProgramTest::new("solcircle_arcium", solcircle_arcium::ID, None),
+            start_slot: None,
+        }
+    }
+}
+
+impl SetupBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load another compiled program alongside this one
+    pub fn program(&mut self, name: &'static str, program_id: Pubkey) -> &mut Self {
+        self.program_test.add_program(name, program_id, None);
+        self
+    }
+
+    pub fn account(&mut self, address: Pubkey, account: Account) -> &mut Self {
+        self.program_test.add_account(address, account);
+        self
+    }
+
+    /// Store `value` at `address`, owned by this program
+    pub fn program_account<T: AccountSerialize>(
+        &mut self,
+        address: Pubkey,
+        value: &T,
+    ) -> &mut Self {
+        self.account(address, program_account(value))
+    }
+
+    /// Fund `address` so it can pay fees and rent
+    pub fn wallet(&mut self, address: Pubkey) -> &mut Self {
+        self.account(
+            address,
+            Account::new(1_000_000_000, 0, &solana_sdk::system_program::ID),
+        )
+    }
+
+    /// Store `config` at the `oracle_config` PDA
+    pub fn oracle_config(&mut self, config: OracleConfig) -> &mut Self {
+        self.program_account(pda(&[b"oracle_config"]).0, &config)
+    }
+
+    /// Warp to `slot` once started
+    pub fn start_at(&mut self, slot: u64) -> &mut Self {
+        self.start_slot = Some(slot);
+        self
+    }
+
+    pub async fn start(self) -> ProgramTestContext {
+        let mut context = self.program_test.start_with_context().await;
+        if let Some(slot) = self.start_slot {
+            context.warp_to_slot(slot).unwrap();
+        }
+        context
+    }
+}
+
+/// A Localnet oracle config with no pending changes and the default MPC timeout
+pub fn oracle_config(authority: Pubkey, oracle_keys: Vec<Pubkey>, threshold: u8) -> OracleConfig {
+    OracleConfig {
+        authority,
+        oracle_keys,
+        threshold,
+        pending_oracle: None,
+        oracle_activation_slot: 0,
+        pending_authority: None,
+        authority_activation_slot: 0,
+        cluster: OracleCluster::Localnet,
+        mpc_timeout_slots: solcircle_arcium::DEFAULT_MPC_TIMEOUT_SLOTS,
+        bump: pda(&[b"oracle_config"]).1,
+    }
+}
+
+/// Send `instructions` in one transaction signed by `signers`; the first pays
+pub async fn send(
+    context: &mut ProgramTestContext,
+    signers: &[&Keypair],
+    instructions: &[Instruction],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(tx).await
+}
+
+pub async fn get_account(context: &mut ProgramTestContext, address: Pubkey) -> Option<Account> {
+    context.banks_client.get_account(address).await.unwrap()
+}
+
+/// Deserialize the program account at `address`, which must exist
+pub async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = get_account(context, address).await.unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Balance of the SPL Token or Token-2022 account at `address`, which must exist
+pub async fn token_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    let account = get_account(context, address).await.unwrap();
+    // Both programs keep the base account layout first, extensions after
+    spl_token::state::Account::unpack_from_slice(&account.data[..spl_token::state::Account::LEN])
+        .unwrap()
+        .amount
+}
+
+pub fn program_account<T: AccountSerialize>(value: &T) -> Account {
+    let mut data = Vec::new();
+    value.try_serialize(&mut data).unwrap();
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: solcircle_arcium::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub const DECIMALS: u8 = 6;
+
+pub fn mint_account() -> Account {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: DECIMALS,
+        is_initialized: true,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Rent-exempt reserve of a 165-byte token account
+pub const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+
+pub fn wsol_account(owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: spl_token::native_mint::ID,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: COption::Some(TOKEN_ACCOUNT_RENT),
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: TOKEN_ACCOUNT_RENT + amount,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &solcircle_arcium::ID)
+}
+
+pub fn assert_error(result: Result<(), BanksClientError>, ix_index: u8, error: ErrorCode) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(ix_index, InstructionError::Custom(error.into()))
+    );
+}
+
+/// Build an Ed25519 program instruction with all data stored inline
+pub fn ed25519_ix(pubkey: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let pubkey_offset: u16 = 2 + 14;
+    let signature_offset: u16 = pubkey_offset + 32;
+    let message_offset: u16 = signature_offset + 64;
+
+    let mut data = vec![1u8, 0u8];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        pubkey_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+pub fn signed_by(signer: &Keypair, message: &[u8]) -> Instruction {
+    let signature: [u8; 64] = signer.sign_message(message).as_ref().try_into().unwrap();
+    ed25519_ix(&signer.pubkey(), &signature, message)
+}
@@ -0,0 +1,119 @@
+// Dark pool close and settlement tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::{assert_error, fetch, mint_account, pda, send, SetupBuilder};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{Circuit, ConfidentialGroupPool, ErrorCode};
+
+const GROUP_ID: &str = "dark-circle";
+
+struct Setup {
+    context: ProgramTestContext,
+    admin: Keypair,
+    confidential_pool: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let admin = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let (confidential_pool, pool_bump) =
+        pda(&[b"confidential_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (_, vault_bump) = pda(&[
+        b"confidential_group_vault",
+        GROUP_ID.as_bytes(),
+        mint.as_ref(),
+    ]);
+
+    builder.account(mint, mint_account()).program_account(
+        confidential_pool,
+        &ConfidentialGroupPool {
+            admin: admin.pubkey(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Dark Circle".to_string(),
+            total_nonce: 0,
+            encrypted_total: [0u8; 32],
+            total_initialized: false,
+            participants_nonce: 0,
+            encrypted_participants: [0u8; 32],
+            participant_count: 0,
+            is_active: true,
+            meets_minimum_threshold: false,
+            pending_request: None,
+            bump: pool_bump,
+            vault_bump,
+        },
+    );
+    builder.wallet(admin.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        admin,
+        confidential_pool,
+    }
+}
+
+fn close_ix(setup: &Setup, admin: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::CloseConfidentialPool {
+            confidential_pool: setup.confidential_pool,
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::CloseConfidentialPool {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn only_admin_closes_pool_once() {
+    let mut setup = setup().await;
+    let admin = setup.admin.pubkey();
+    let stranger = Keypair::new();
+
+    let ix = close_ix(&setup, &stranger.pubkey());
+    let result = send(&mut setup.context, &[&setup.admin, &stranger], &[ix]).await;
+    assert_error(result, 0, ErrorCode::Unauthorized);
+
+    let ix = close_ix(&setup, &admin);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let pool: ConfidentialGroupPool = fetch(&mut setup.context, setup.confidential_pool).await;
+    assert!(!pool.is_active);
+
+    let ix = close_ix(&setup, &admin);
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+    assert_error(result, 0, ErrorCode::PoolNotActive);
+}
+
+#[test]
+fn settlement_holds_deposit_pool_and_member() {
+    // All must be unlocked again by `expire_mpc_request` if no callback lands
+    assert!(Circuit::ComputeUserShare.locks_deposit());
+    assert!(Circuit::ComputeUserShare.locks_pool());
+    assert!(Circuit::ComputeUserShare.locks_member());
+    assert!(!Circuit::ComputeUserShare.locks_proposal());
+}
+
+#[test]
+fn pool_stats_hold_the_pool() {
+    // Counted from the encrypted total, so no transfer may rewrite it
+    // between queueing and the callback
+    assert!(Circuit::ComputeAggregateStats.locks_pool());
+    assert!(!Circuit::ComputeAggregateStats.locks_deposit());
+    assert!(!Circuit::ComputeAggregateStats.locks_member());
+    assert!(!Circuit::ComputeAggregateStats.locks_proposal());
+}
@@ -0,0 +1,223 @@
+// Confidential vote expiry tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::{assert_error, fetch, get_account, oracle_config, pda, send, SetupBuilder};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{
+    Circuit, ConfidentialMemberPosition, ConfidentialProposal, ConfidentialVoteRecord, ErrorCode,
+    MpcRequest, MpcRequestStatus, OracleConfig, ProposalAction, ProposalStatus,
+};
+
+const PROPOSAL_ID: u64 = 1;
+const CREATED_SLOT: u64 = 10;
+const TIMEOUT_SLOTS: u64 = 100;
+
+struct Setup {
+    context: ProgramTestContext,
+    alice: Keypair,
+    mpc_request: Pubkey,
+    proposal: Pubkey,
+    alice_member: Pubkey,
+    bob_member: Pubkey,
+    vote_record: Pubkey,
+}
+
+fn member_position(
+    pool: Pubkey,
+    member: Pubkey,
+    bump: u8,
+    lock: Option<Pubkey>,
+) -> ConfidentialMemberPosition {
+    ConfidentialMemberPosition {
+        pool,
+        member,
+        contribution_nonce: 0,
+        encrypted_contribution: [0u8; 32],
+        contribution_initialized: true,
+        pending_request: lock,
+        last_update_slot: 0,
+        bump,
+    }
+}
+
+// Alice's vote is queued and holds the tally and her position, but the
+// MPC never calls back
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let alice = Keypair::new();
+    let bob = Pubkey::new_unique();
+    let pool = Pubkey::new_unique();
+
+    let (proposal, proposal_bump) = pda(&[
+        b"confidential_proposal",
+        pool.as_ref(),
+        &PROPOSAL_ID.to_le_bytes(),
+    ]);
+    let (alice_member, alice_member_bump) = pda(&[
+        b"confidential_member",
+        pool.as_ref(),
+        alice.pubkey().as_ref(),
+    ]);
+    let (bob_member, bob_member_bump) = pda(&[b"confidential_member", pool.as_ref(), bob.as_ref()]);
+    let (vote_record, vote_bump) = pda(&[
+        b"confidential_vote",
+        proposal.as_ref(),
+        alice.pubkey().as_ref(),
+    ]);
+    let (mpc_request, request_bump) =
+        pda(&[b"mpc_request", alice.pubkey().as_ref(), &0u64.to_le_bytes()]);
+
+    builder.oracle_config(OracleConfig {
+        mpc_timeout_slots: TIMEOUT_SLOTS,
+        ..oracle_config(Pubkey::new_unique(), vec![Pubkey::new_unique()], 1)
+    });
+    builder.program_account(
+        mpc_request,
+        &MpcRequest {
+            requester: alice.pubkey(),
+            target_account: proposal,
+            pool,
+            circuit: Circuit::AddWeightedVote,
+            status: MpcRequestStatus::Pending,
+            created_slot: CREATED_SLOT,
+            bump: request_bump,
+        },
+    );
+    builder.program_account(
+        proposal,
+        &ConfidentialProposal {
+            pool,
+            proposal_id: PROPOSAL_ID,
+            proposer: alice.pubkey(),
+            action: ProposalAction::Buy,
+            token: Pubkey::new_unique(),
+            amount: 1_000,
+            created_slot: CREATED_SLOT,
+            deadline_slot: u64::MAX,
+            quorum_bps: 5_000,
+            total_nonce: 0,
+            encrypted_total: [0u8; 32],
+            total_initialized: true,
+            tally_nonce: 0,
+            encrypted_tally: [[0u8; 32]; 2],
+            tally_initialized: false,
+            vote_count: 0,
+            turnout_bps: 0,
+            status: ProposalStatus::Voting,
+            pending_request: Some(mpc_request),
+            bump: proposal_bump,
+        },
+    );
+    builder
+        .program_account(
+            alice_member,
+            &member_position(pool, alice.pubkey(), alice_member_bump, Some(mpc_request)),
+        )
+        .program_account(
+            bob_member,
+            &member_position(pool, bob, bob_member_bump, None),
+        )
+        .program_account(
+            vote_record,
+            &ConfidentialVoteRecord {
+                proposal,
+                voter: alice.pubkey(),
+                bump: vote_bump,
+            },
+        )
+        .wallet(alice.pubkey())
+        .start_at(CREATED_SLOT + TIMEOUT_SLOTS);
+
+    Setup {
+        context: builder.start().await,
+        alice,
+        mpc_request,
+        proposal,
+        alice_member,
+        bob_member,
+        vote_record,
+    }
+}
+
+fn expire_ix(setup: &Setup, member: Option<Pubkey>, vote_record: Option<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ExpireMpcRequest {
+            mpc_request: setup.mpc_request,
+            requester: setup.alice.pubkey(),
+            oracle_config: pda(&[b"oracle_config"]).0,
+            confidential_deposit: None,
+            confidential_pool: None,
+            confidential_proposal: Some(setup.proposal),
+            confidential_member: member,
+            vote_record,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ExpireMpcRequest {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn expired_vote_unlocks_member_and_can_be_recast() {
+    let mut setup = setup().await;
+
+    let ix = expire_ix(&setup, Some(setup.alice_member), Some(setup.vote_record));
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+
+    let proposal: ConfidentialProposal = fetch(&mut setup.context, setup.proposal).await;
+    assert_eq!(proposal.pending_request, None);
+    assert_eq!(proposal.vote_count, 0);
+    let member: ConfidentialMemberPosition = fetch(&mut setup.context, setup.alice_member).await;
+    assert_eq!(member.pending_request, None);
+    // Never counted, so the record is gone and Alice can vote again
+    assert!(get_account(&mut setup.context, setup.vote_record)
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn rejects_expiry_without_the_vote_record() {
+    let mut setup = setup().await;
+
+    let ix = expire_ix(&setup, Some(setup.alice_member), None);
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestMismatch);
+}
+
+#[tokio::test]
+async fn rejects_expiry_with_another_members_position() {
+    let mut setup = setup().await;
+
+    // Bob's position would be left alone and Alice's locked for good
+    let ix = expire_ix(&setup, Some(setup.bob_member), Some(setup.vote_record));
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestMismatch);
+    let member: ConfidentialMemberPosition = fetch(&mut setup.context, setup.alice_member).await;
+    assert_eq!(member.pending_request, Some(setup.mpc_request));
+}
+
+#[test]
+fn vote_holds_tally_and_member() {
+    assert!(Circuit::AddWeightedVote.locks_proposal());
+    assert!(Circuit::AddWeightedVote.locks_member());
+    assert!(!Circuit::AddWeightedVote.locks_deposit());
+    assert!(!Circuit::AddWeightedVote.locks_pool());
+    assert!(Circuit::ConfidentialTransferToPool.locks_member());
+    assert!(Circuit::ConfidentialTransferFromPool.locks_member());
+    assert!(!Circuit::TallyVotes.locks_member());
+}
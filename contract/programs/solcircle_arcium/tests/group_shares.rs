@@ -0,0 +1,259 @@
+// Group pool share accounting tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    fetch, mint_account, oracle_config, pda, send, signed_by, token_account, SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+use solcircle_arcium::{
+    GroupPool, MemberPosition, OracleAction, OracleAmount, OracleAuthorization, OracleCluster,
+    Session, SessionStatus, UserDeposit, ORACLE_DOMAIN,
+};
+
+const GROUP_ID: &str = "circle";
+const ALICE_SHARES: u64 = 1_000;
+// Alice's 1_000 has doubled while the pool traded
+const POOL_NAV: u64 = 2_000;
+const BOB_DEPOSIT: u64 = 1_000;
+// Enough to double the share price if the vault balance were the NAV
+const DONATION: u64 = POOL_NAV + BOB_DEPOSIT;
+const EXPIRY_SLOT: u64 = 1_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    oracle: Keypair,
+    alice: Pubkey,
+    bob: Keypair,
+    bob_wallet: Pubkey,
+    mint: Pubkey,
+    group_pool: Pubkey,
+    group_vault: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let oracle = Keypair::new();
+    let alice = Pubkey::new_unique();
+    let bob = Keypair::new();
+    let bob_wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (group_pool, pool_bump) = pda(&[b"group_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (group_vault, group_vault_bump) =
+        pda(&[b"group_vault", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (session, session_bump) = pda(&[b"session", group_pool.as_ref()]);
+    let (alice_position, alice_position_bump) =
+        pda(&[b"member_position", group_pool.as_ref(), alice.as_ref()]);
+    let (bob_deposit, bob_deposit_bump) =
+        pda(&[b"user_deposit", bob.pubkey().as_ref(), mint.as_ref()]);
+    let (bob_vault, bob_vault_bump) = pda(&[b"user_vault", bob.pubkey().as_ref(), mint.as_ref()]);
+
+    builder.account(mint, mint_account());
+    builder.program_account(
+        group_pool,
+        &GroupPool {
+            admin: Pubkey::new_unique(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Circle".to_string(),
+            total_locked: ALICE_SHARES,
+            total_shares: ALICE_SHARES,
+            net_asset_value: POOL_NAV,
+            is_active: true,
+            open_positions: vec![],
+            bump: pool_bump,
+            vault_bump: group_vault_bump,
+        },
+    );
+    builder.account(group_vault, token_account(mint, group_vault, POOL_NAV));
+    builder.program_account(
+        session,
+        &Session {
+            pool: group_pool,
+            session_id: 0,
+            start_slot: EXPIRY_SLOT,
+            end_slot: EXPIRY_SLOT + 1,
+            status: SessionStatus::Open,
+            bump: session_bump,
+        },
+    );
+    builder.oracle_config(oracle_config(
+        Pubkey::new_unique(),
+        vec![oracle.pubkey()],
+        1,
+    ));
+    builder.program_account(
+        alice_position,
+        &MemberPosition {
+            pool: group_pool,
+            member: alice,
+            contributed_amount: ALICE_SHARES,
+            shares: ALICE_SHARES,
+            last_increase_slot: 0,
+            bump: alice_position_bump,
+        },
+    );
+    builder.program_account(
+        bob_deposit,
+        &UserDeposit {
+            owner: bob.pubkey(),
+            mint,
+            deposited_amount: BOB_DEPOSIT,
+            oracle_nonce: 0,
+            bump: bob_deposit_bump,
+            vault_bump: bob_vault_bump,
+        },
+    );
+    builder.account(bob_vault, token_account(mint, bob_vault, BOB_DEPOSIT));
+    builder.account(bob_wallet, token_account(mint, bob.pubkey(), DONATION));
+    builder.wallet(bob.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        oracle,
+        alice,
+        bob,
+        bob_wallet,
+        mint,
+        group_pool,
+        group_vault,
+    }
+}
+
+fn release_message(setup: &Setup, amount: u64) -> Vec<u8> {
+    let authorization = OracleAuthorization {
+        domain: ORACLE_DOMAIN,
+        program_id: solcircle_arcium::ID,
+        cluster: OracleCluster::Localnet,
+        action: OracleAction::ReleaseToGroup,
+        user: setup.bob.pubkey(),
+        group: setup.group_pool,
+        mint: setup.mint,
+        amount: OracleAmount::Plain(amount),
+        nonce: 0,
+        expiry_slot: EXPIRY_SLOT,
+    };
+    let mut message = Vec::new();
+    authorization.serialize(&mut message).unwrap();
+    message
+}
+
+fn release_ix(setup: &Setup, amount: u64) -> Instruction {
+    let user = setup.bob.pubkey();
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ReleaseFundsToGroup {
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            group_pool: setup.group_pool,
+            group_vault: pda(&[b"group_vault", GROUP_ID.as_bytes(), setup.mint.as_ref()]).0,
+            session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+            member_position: member_position(setup, &user),
+            user,
+            oracle_config: pda(&[b"oracle_config"]).0,
+            instructions_sysvar: sysvar::instructions::ID,
+            mint: setup.mint,
+            payer: user,
+            token_program: spl_token::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ReleaseFundsToGroup {
+            amount,
+            nonce: 0,
+            expiry_slot: EXPIRY_SLOT,
+        }
+        .data(),
+    }
+}
+
+fn member_position(setup: &Setup, member: &Pubkey) -> Pubkey {
+    pda(&[
+        b"member_position",
+        setup.group_pool.as_ref(),
+        member.as_ref(),
+    ])
+    .0
+}
+
+#[tokio::test]
+async fn late_joiner_buys_shares_at_nav() {
+    let mut setup = setup().await;
+
+    let message = release_message(&setup, BOB_DEPOSIT);
+    let instructions = [
+        signed_by(&setup.oracle, &message),
+        release_ix(&setup, BOB_DEPOSIT),
+    ];
+    send(&mut setup.context, &[&setup.bob], &instructions)
+        .await
+        .unwrap();
+
+    // Each share is worth 2 tokens, so Bob's 1_000 buys 500 of them
+    let bob = member_position(&setup, &setup.bob.pubkey());
+    let bob_position: MemberPosition = fetch(&mut setup.context, bob).await;
+    assert_eq!(bob_position.shares, BOB_DEPOSIT / 2);
+    assert_eq!(bob_position.contributed_amount, BOB_DEPOSIT);
+
+    // Alice still owns everything the pool made before Bob joined
+    let group_pool = setup.group_pool;
+    let pool: GroupPool = fetch(&mut setup.context, group_pool).await;
+    assert_eq!(pool.total_shares, ALICE_SHARES + BOB_DEPOSIT / 2);
+    let nav = POOL_NAV + BOB_DEPOSIT;
+    assert_eq!(pool.value_of(ALICE_SHARES, nav), POOL_NAV);
+    assert_eq!(pool.value_of(bob_position.shares, nav), BOB_DEPOSIT);
+
+    let alice = member_position(&setup, &setup.alice);
+    let alice_position: MemberPosition = fetch(&mut setup.context, alice).await;
+    assert_eq!(alice_position.shares, ALICE_SHARES);
+}
+
+#[tokio::test]
+async fn donation_does_not_move_share_price() {
+    let mut setup = setup().await;
+
+    // Tokens sent straight to the vault ahead of Bob's deposit
+    let donation = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &setup.bob_wallet,
+        &setup.group_vault,
+        &setup.bob.pubkey(),
+        &[],
+        DONATION,
+    )
+    .unwrap();
+    let message = release_message(&setup, BOB_DEPOSIT);
+    let instructions = [
+        donation,
+        signed_by(&setup.oracle, &message),
+        release_ix(&setup, BOB_DEPOSIT),
+    ];
+    send(&mut setup.context, &[&setup.bob], &instructions)
+        .await
+        .unwrap();
+
+    // Bob pays the same price as without the donation
+    let bob = member_position(&setup, &setup.bob.pubkey());
+    let bob_position: MemberPosition = fetch(&mut setup.context, bob).await;
+    assert_eq!(bob_position.shares, BOB_DEPOSIT / 2);
+
+    // The donation isn't counted, so Alice's value hasn't moved either
+    let group_pool = setup.group_pool;
+    let pool: GroupPool = fetch(&mut setup.context, group_pool).await;
+    let nav = pool.nav().unwrap();
+    assert_eq!(nav, POOL_NAV + BOB_DEPOSIT);
+    assert_eq!(pool.value_of(ALICE_SHARES, nav), POOL_NAV);
+    assert_eq!(pool.value_of(bob_position.shares, nav), BOB_DEPOSIT);
+}
@@ -0,0 +1,228 @@
+// Member position entitlement and view tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{AnchorDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, fetch, mint_account, oracle_config, pda, program_account, send, token_account,
+    SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+use solcircle_arcium::{
+    ErrorCode, GroupPool, MemberPosition, MemberStake, Session, SessionStatus, UserDeposit,
+};
+
+const GROUP_ID: &str = "circle";
+const ALICE_LOCKED: u64 = 1_000;
+const BOB_LOCKED: u64 = 2_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    alice: Keypair,
+    mint: Pubkey,
+    group_pool: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let (group_pool, pool_bump) = pda(&[b"group_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (group_vault, group_vault_bump) =
+        pda(&[b"group_vault", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (session, session_bump) = pda(&[b"session", group_pool.as_ref()]);
+
+    builder.account(mint, mint_account()).program_account(
+        group_pool,
+        &GroupPool {
+            admin: Pubkey::new_unique(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Circle".to_string(),
+            total_locked: ALICE_LOCKED + BOB_LOCKED,
+            total_shares: ALICE_LOCKED + BOB_LOCKED,
+            net_asset_value: ALICE_LOCKED + BOB_LOCKED,
+            is_active: true,
+            open_positions: vec![],
+            bump: pool_bump,
+            vault_bump: group_vault_bump,
+        },
+    );
+    builder.account(
+        group_vault,
+        token_account(mint, group_vault, ALICE_LOCKED + BOB_LOCKED),
+    );
+    builder.program_account(
+        session,
+        &Session {
+            pool: group_pool,
+            session_id: 0,
+            start_slot: 0,
+            end_slot: 1,
+            status: SessionStatus::Closed,
+            bump: session_bump,
+        },
+    );
+    builder.oracle_config(oracle_config(
+        Pubkey::new_unique(),
+        vec![Pubkey::new_unique()],
+        1,
+    ));
+
+    for (member, locked) in [(&alice, ALICE_LOCKED), (&bob, BOB_LOCKED)] {
+        let member = member.pubkey();
+        let (position, position_bump) =
+            pda(&[b"member_position", group_pool.as_ref(), member.as_ref()]);
+        let (user_deposit, deposit_bump) = pda(&[b"user_deposit", member.as_ref(), mint.as_ref()]);
+        let (user_vault, vault_bump) = pda(&[b"user_vault", member.as_ref(), mint.as_ref()]);
+
+        builder.program_account(
+            position,
+            &MemberPosition {
+                pool: group_pool,
+                member,
+                contributed_amount: locked,
+                shares: locked,
+                last_increase_slot: 0,
+                bump: position_bump,
+            },
+        );
+        builder.program_account(
+            user_deposit,
+            &UserDeposit {
+                owner: member,
+                mint,
+                deposited_amount: 0,
+                oracle_nonce: 0,
+                bump: deposit_bump,
+                vault_bump,
+            },
+        );
+        builder.account(user_vault, token_account(mint, user_vault, 0));
+    }
+    builder.wallet(alice.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        alice,
+        mint,
+        group_pool,
+    }
+}
+
+fn member_position(setup: &Setup, member: &Pubkey) -> Pubkey {
+    pda(&[
+        b"member_position",
+        setup.group_pool.as_ref(),
+        member.as_ref(),
+    ])
+    .0
+}
+
+fn view_ix(setup: &Setup, member: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::GetMemberPosition {
+            group_pool: setup.group_pool,
+            member_position: member_position(setup, member),
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::GetMemberPosition {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn rejects_release_beyond_member_entitlement() {
+    let mut setup = setup().await;
+    let user = setup.alice.pubkey();
+
+    // The pool holds enough, but only `ALICE_LOCKED` of it is hers
+    let ix = Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ReleaseFundsFromGroup {
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            group_pool: setup.group_pool,
+            group_vault: pda(&[b"group_vault", GROUP_ID.as_bytes(), setup.mint.as_ref()]).0,
+            session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+            member_position: member_position(&setup, &user),
+            user,
+            oracle_config: pda(&[b"oracle_config"]).0,
+            instructions_sysvar: sysvar::instructions::ID,
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ReleaseFundsFromGroup {
+            amount: ALICE_LOCKED + 1,
+            nonce: 0,
+            expiry_slot: u64::MAX,
+        }
+        .data(),
+    };
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::ExceedsEntitlement);
+}
+
+#[tokio::test]
+async fn view_returns_member_stake() {
+    let mut setup = setup().await;
+    let user = setup.alice.pubkey();
+
+    let ix = view_ix(&setup, &user);
+    let blockhash = setup.context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&user), &[&setup.alice], blockhash);
+    let simulation = setup
+        .context
+        .banks_client
+        .simulate_transaction(tx)
+        .await
+        .unwrap();
+
+    let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+    let stake = MemberStake::try_from_slice(&return_data.data).unwrap();
+    assert_eq!(
+        stake,
+        MemberStake {
+            pool: setup.group_pool,
+            member: user,
+            contributed_amount: ALICE_LOCKED,
+            shares: ALICE_LOCKED,
+            entitlement: ALICE_LOCKED,
+            pool_total_locked: ALICE_LOCKED + BOB_LOCKED,
+            pool_total_shares: ALICE_LOCKED + BOB_LOCKED,
+            pool_nav: ALICE_LOCKED + BOB_LOCKED,
+        }
+    );
+}
+
+#[tokio::test]
+async fn view_has_no_entitlement_while_pool_holds_tokens() {
+    let mut setup = setup().await;
+    let user = setup.alice.pubkey();
+
+    // The pool has bought a token it can't price
+    let mut pool: GroupPool = fetch(&mut setup.context, setup.group_pool).await;
+    pool.open_positions.push(Pubkey::new_unique());
+    setup
+        .context
+        .set_account(&setup.group_pool, &program_account(&pool).into());
+
+    let ix = view_ix(&setup, &user);
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+    assert_error(result, 0, ErrorCode::PoolHasOpenPositions);
+}
@@ -0,0 +1,363 @@
+// MPC request lifecycle tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{AnchorSerialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, fetch, get_account, mint_account, oracle_config, pda, send, token_account,
+    token_balance, SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{
+    Circuit, ConfidentialUserDeposit, ErrorCode, MpcRequest, MpcRequestStatus, OracleConfig,
+};
+
+const CREATED_SLOT: u64 = 10;
+const TIMEOUT_SLOTS: u64 = 100;
+const ESCROWED: u64 = 2_500;
+
+struct Setup {
+    context: ProgramTestContext,
+    payer: Keypair,
+    mpc_request: Pubkey,
+    // Left open by an older program version, in its layout
+    legacy_request: Pubkey,
+    confidential_deposit: Pubkey,
+    deposit_escrow: Pubkey,
+    mint: Pubkey,
+    user_token_account: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    // The payer is also the depositor, so it can sign for refunds
+    let payer = Keypair::new();
+    let requester = payer.pubkey();
+    let mint = Pubkey::new_unique();
+    let user_token_account = Pubkey::new_unique();
+
+    let (confidential_deposit, deposit_bump) =
+        pda(&[b"confidential_deposit", requester.as_ref(), mint.as_ref()]);
+    let (mpc_request, request_bump) =
+        pda(&[b"mpc_request", requester.as_ref(), &0u64.to_le_bytes()]);
+    let (deposit_escrow, _) = pda(&[b"deposit_escrow", mpc_request.as_ref()]);
+    let (legacy_request, legacy_bump) = pda(&[
+        b"mpc_request",
+        requester.as_ref(),
+        &CREATED_SLOT.to_le_bytes(),
+    ]);
+
+    builder.oracle_config(OracleConfig {
+        mpc_timeout_slots: TIMEOUT_SLOTS,
+        ..oracle_config(Pubkey::new_unique(), vec![Pubkey::new_unique()], 1)
+    });
+    builder.program_account(
+        mpc_request,
+        &MpcRequest {
+            requester,
+            target_account: confidential_deposit,
+            pool: Pubkey::default(),
+            circuit: Circuit::AddToBalance,
+            status: MpcRequestStatus::Pending,
+            created_slot: CREATED_SLOT,
+            bump: request_bump,
+        },
+    );
+    let mut data = MpcRequest::DISCRIMINATOR.to_vec();
+    (
+        requester,
+        confidential_deposit,
+        MpcRequestStatus::Pending,
+        CREATED_SLOT,
+        legacy_bump,
+    )
+        .serialize(&mut data)
+        .unwrap();
+    builder.account(
+        legacy_request,
+        Account {
+            lamports: 1_000_000_000,
+            data,
+            owner: solcircle_arcium::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    builder.program_account(
+        confidential_deposit,
+        &ConfidentialUserDeposit {
+            owner: requester,
+            mint,
+            encryption_pubkey: [7u8; 32],
+            balance_nonce: 0,
+            encrypted_balance: [0u8; 32],
+            balance_initialized: false,
+            last_update_slot: CREATED_SLOT,
+            is_active: true,
+            oracle_nonce: 0,
+            pending_request: Some(mpc_request),
+            bump: deposit_bump,
+            vault_bump: pda(&[b"confidential_vault", requester.as_ref(), mint.as_ref()]).1,
+        },
+    );
+    builder
+        .account(
+            deposit_escrow,
+            token_account(mint, deposit_escrow, ESCROWED),
+        )
+        .account(mint, mint_account())
+        .account(user_token_account, token_account(mint, requester, 0))
+        .wallet(payer.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        payer,
+        mpc_request,
+        legacy_request,
+        confidential_deposit,
+        deposit_escrow,
+        mint,
+        user_token_account,
+    }
+}
+
+fn expire_ix(setup: &Setup, confidential_deposit: Option<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ExpireMpcRequest {
+            mpc_request: setup.mpc_request,
+            requester: setup.payer.pubkey(),
+            oracle_config: pda(&[b"oracle_config"]).0,
+            confidential_deposit,
+            confidential_pool: None,
+            confidential_proposal: None,
+            confidential_member: None,
+            vote_record: None,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ExpireMpcRequest {}.data(),
+    }
+}
+
+fn refund_ix(setup: &Setup) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::RefundConfidentialDeposit {
+            mpc_request: setup.mpc_request,
+            deposit_escrow: setup.deposit_escrow,
+            user_token_account: Some(setup.user_token_account),
+            user: setup.payer.pubkey(),
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::RefundConfidentialDeposit {}.data(),
+    }
+}
+
+fn reclaim_ix(setup: &Setup, request: Pubkey) -> Instruction {
+    let mut accounts = solcircle_arcium::accounts::ReclaimMpcRequests {
+        requester: setup.payer.pubkey(),
+        oracle_config: pda(&[b"oracle_config"]).0,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(request, false));
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts,
+        data: solcircle_arcium::instruction::ReclaimMpcRequests {}.data(),
+    }
+}
+
+async fn request_status(setup: &mut Setup) -> MpcRequestStatus {
+    fetch::<MpcRequest>(&mut setup.context, setup.mpc_request)
+        .await
+        .status
+}
+
+async fn deposit_lock(setup: &mut Setup) -> Option<Pubkey> {
+    fetch::<ConfidentialUserDeposit>(&mut setup.context, setup.confidential_deposit)
+        .await
+        .pending_request
+}
+
+#[tokio::test]
+async fn rejects_expiry_before_timeout() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS - 1)
+        .unwrap();
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestNotExpired);
+    assert!(request_status(&mut setup).await == MpcRequestStatus::Pending);
+    assert_eq!(deposit_lock(&mut setup).await, Some(setup.mpc_request));
+}
+
+#[tokio::test]
+async fn expiry_unlocks_the_deposit() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+
+    assert_eq!(deposit_lock(&mut setup).await, None);
+}
+
+#[tokio::test]
+async fn rejects_expiry_without_the_locked_deposit() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+
+    let ix = expire_ix(&setup, None);
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestMismatch);
+}
+
+#[tokio::test]
+async fn expires_stale_request_only_once() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+    assert!(request_status(&mut setup).await == MpcRequestStatus::Failed);
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+    assert_error(result, 0, ErrorCode::MpcRequestNotPending);
+}
+
+#[tokio::test]
+async fn rejects_refund_while_request_is_pending() {
+    let mut setup = setup().await;
+
+    let ix = refund_ix(&setup);
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestNotFailed);
+    assert_eq!(
+        token_balance(&mut setup.context, setup.deposit_escrow).await,
+        ESCROWED
+    );
+}
+
+#[tokio::test]
+async fn refunds_escrow_after_expiry() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+    let ix = refund_ix(&setup);
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        ESCROWED
+    );
+    // Closed, so the same escrow can't be refunded twice
+    assert!(get_account(&mut setup.context, setup.deposit_escrow)
+        .await
+        .is_none());
+    assert!(get_account(&mut setup.context, setup.mpc_request)
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn reclaim_keeps_failed_deposit_until_refunded() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+
+    let ix = expire_ix(&setup, Some(setup.confidential_deposit));
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+
+    let ix = reclaim_ix(&setup, setup.mpc_request);
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+    assert_error(result, 0, ErrorCode::MpcRequestHasEscrow);
+}
+
+#[tokio::test]
+async fn reclaim_rejects_pending_request() {
+    let mut setup = setup().await;
+
+    let ix = reclaim_ix(&setup, setup.mpc_request);
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestNotSettled);
+}
+
+#[tokio::test]
+async fn reclaim_closes_legacy_request_after_timeout() {
+    let mut setup = setup().await;
+    setup
+        .context
+        .warp_to_slot(CREATED_SLOT + TIMEOUT_SLOTS)
+        .unwrap();
+    let payer = setup.payer.pubkey();
+    let before = setup.context.banks_client.get_balance(payer).await.unwrap();
+
+    let ix = reclaim_ix(&setup, setup.legacy_request);
+    send(&mut setup.context, &[&setup.payer], &[ix])
+        .await
+        .unwrap();
+
+    assert!(get_account(&mut setup.context, setup.legacy_request)
+        .await
+        .is_none());
+    // Its rent comes back, less the transaction fee
+    let after = setup.context.banks_client.get_balance(payer).await.unwrap();
+    assert!(after > before + 900_000_000);
+}
+
+#[tokio::test]
+async fn reclaim_keeps_legacy_request_until_timeout() {
+    let mut setup = setup().await;
+
+    let ix = reclaim_ix(&setup, setup.legacy_request);
+    let result = send(&mut setup.context, &[&setup.payer], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MpcRequestNotExpired);
+}
@@ -0,0 +1,108 @@
+// Native SOL (wSOL) deposit tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, fetch, pda, send, token_account, token_balance, wsol_account, SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{ErrorCode, UserDeposit};
+
+const WRAPPED: u64 = 5_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    user: Keypair,
+    mint: Pubkey,
+}
+
+async fn setup(mint: Pubkey) -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let user = Keypair::new();
+    let (user_deposit, deposit_bump) =
+        pda(&[b"user_deposit", user.pubkey().as_ref(), mint.as_ref()]);
+    let (user_vault, vault_bump) = pda(&[b"user_vault", user.pubkey().as_ref(), mint.as_ref()]);
+
+    builder.program_account(
+        user_deposit,
+        &UserDeposit {
+            owner: user.pubkey(),
+            mint,
+            deposited_amount: WRAPPED,
+            oracle_nonce: 0,
+            bump: deposit_bump,
+            vault_bump,
+        },
+    );
+    let vault = if mint == spl_token::native_mint::ID {
+        wsol_account(user_vault, WRAPPED)
+    } else {
+        token_account(mint, user_vault, WRAPPED)
+    };
+    builder.account(user_vault, vault).wallet(user.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        user,
+        mint,
+    }
+}
+
+fn deposit_sol_ix(setup: &Setup, lamports: u64) -> Instruction {
+    let user = setup.user.pubkey();
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::DepositSolToUserPda {
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            user,
+            owner: user,
+            token_program: spl_token::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::DepositSolToUserPda { lamports }.data(),
+    }
+}
+
+#[tokio::test]
+async fn wraps_deposited_lamports_into_the_vault() {
+    let mut setup = setup(spl_token::native_mint::ID).await;
+    let user = setup.user.pubkey();
+
+    let ix = deposit_sol_ix(&setup, 1_000);
+    send(&mut setup.context, &[&setup.user], &[ix])
+        .await
+        .unwrap();
+
+    let vault = pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0;
+    assert_eq!(
+        token_balance(&mut setup.context, vault).await,
+        WRAPPED + 1_000
+    );
+
+    let deposit = pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0;
+    let deposit: UserDeposit = fetch(&mut setup.context, deposit).await;
+    assert_eq!(deposit.deposited_amount, WRAPPED + 1_000);
+}
+
+#[tokio::test]
+async fn rejects_sol_deposit_to_spl_deposit() {
+    let mut setup = setup(Pubkey::new_unique()).await;
+
+    let ix = deposit_sol_ix(&setup, 1_000);
+    let result = send(&mut setup.context, &[&setup.user], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::MintMismatch);
+}
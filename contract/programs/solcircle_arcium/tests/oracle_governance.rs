@@ -0,0 +1,261 @@
+// Timelocked oracle set and authority changes
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::{assert_error, fetch, oracle_config, pda, send, SetupBuilder};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{ErrorCode, OracleChange, OracleConfig, ORACLE_TIMELOCK_SLOTS};
+
+const START_SLOT: u64 = 10;
+
+struct Setup {
+    context: ProgramTestContext,
+    authority: Keypair,
+    oracles: Vec<Pubkey>,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let authority = Keypair::new();
+    let oracles: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+
+    builder
+        .oracle_config(oracle_config(authority.pubkey(), oracles.clone(), 2))
+        .wallet(authority.pubkey())
+        .start_at(START_SLOT);
+
+    Setup {
+        context: builder.start().await,
+        authority,
+        oracles,
+    }
+}
+
+fn update_ix(setup: &Setup, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::UpdateOracle {
+            oracle_config: pda(&[b"oracle_config"]).0,
+            authority: setup.authority.pubkey(),
+        }
+        .to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn accept_authority_ix(new_authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::AcceptAuthority {
+            oracle_config: pda(&[b"oracle_config"]).0,
+            new_authority: *new_authority,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::AcceptAuthority {}.data(),
+    }
+}
+
+async fn get_config(setup: &mut Setup) -> OracleConfig {
+    fetch(&mut setup.context, pda(&[b"oracle_config"]).0).await
+}
+
+// With some slack for the slots fetching a fresh blockhash can take
+fn past_timelock() -> u64 {
+    START_SLOT + ORACLE_TIMELOCK_SLOTS + 100
+}
+
+#[tokio::test]
+async fn oracle_key_added_only_after_timelock() {
+    let mut setup = setup().await;
+    let new_oracle = Pubkey::new_unique();
+
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeOracle {
+            oracle_pubkey: new_oracle,
+        },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_config(&mut setup).await.pending_oracle,
+        Some(OracleChange::Add(new_oracle))
+    );
+
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    let result = send(&mut setup.context, &[&setup.authority], &[ix]).await;
+    assert_error(result, 0, ErrorCode::TimelockNotElapsed);
+
+    setup.context.warp_to_slot(past_timelock()).unwrap();
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+
+    let config = get_config(&mut setup).await;
+    assert!(config.oracle_keys.contains(&new_oracle));
+    assert_eq!(config.pending_oracle, None);
+}
+
+#[tokio::test]
+async fn removal_and_threshold_wait_for_timelock() {
+    let mut setup = setup().await;
+    let removed = setup.oracles[0];
+
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeOracleRemoval {
+            oracle_pubkey: removed,
+        },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+
+    // Still signing until the timelock has passed
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    let result = send(&mut setup.context, &[&setup.authority], &[ix]).await;
+    assert_error(result, 0, ErrorCode::TimelockNotElapsed);
+    assert!(get_config(&mut setup).await.oracle_keys.contains(&removed));
+
+    setup.context.warp_to_slot(past_timelock()).unwrap();
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    assert!(!get_config(&mut setup).await.oracle_keys.contains(&removed));
+
+    // Lowering the threshold is just as slow
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeOracleThreshold { threshold: 1 },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    let result = send(&mut setup.context, &[&setup.authority], &[ix]).await;
+    assert_error(result, 0, ErrorCode::TimelockNotElapsed);
+    assert_eq!(get_config(&mut setup).await.threshold, 2);
+
+    setup
+        .context
+        .warp_to_slot(past_timelock() + ORACLE_TIMELOCK_SLOTS + 100)
+        .unwrap();
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_config(&mut setup).await.threshold, 1);
+}
+
+#[tokio::test]
+async fn cancelled_oracle_change_cannot_be_accepted() {
+    let mut setup = setup().await;
+
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeOracleThreshold { threshold: 1 },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::CancelOracleProposal {},
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+
+    setup.context.warp_to_slot(past_timelock()).unwrap();
+    let ix = update_ix(&setup, solcircle_arcium::instruction::AcceptOracle {});
+    let result = send(&mut setup.context, &[&setup.authority], &[ix]).await;
+    assert_error(result, 0, ErrorCode::NoPendingChange);
+    assert_eq!(get_config(&mut setup).await.threshold, 2);
+}
+
+#[tokio::test]
+async fn authority_handover_waits_for_timelock() {
+    let mut setup = setup().await;
+    let new_authority = Keypair::new();
+
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeAuthority {
+            new_authority: new_authority.pubkey(),
+        },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+
+    let ix = accept_authority_ix(&new_authority.pubkey());
+    let result = send(
+        &mut setup.context,
+        &[&setup.authority, &new_authority],
+        &[ix],
+    )
+    .await;
+    assert_error(result, 0, ErrorCode::TimelockNotElapsed);
+
+    setup.context.warp_to_slot(past_timelock()).unwrap();
+    let ix = accept_authority_ix(&new_authority.pubkey());
+    send(
+        &mut setup.context,
+        &[&setup.authority, &new_authority],
+        &[ix],
+    )
+    .await
+    .unwrap();
+
+    let config = get_config(&mut setup).await;
+    assert_eq!(config.authority, new_authority.pubkey());
+    assert_eq!(config.pending_authority, None);
+}
+
+#[tokio::test]
+async fn cancelled_authority_handover_cannot_be_accepted() {
+    let mut setup = setup().await;
+    let new_authority = Keypair::new();
+
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::ProposeAuthority {
+            new_authority: new_authority.pubkey(),
+        },
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+    let ix = update_ix(
+        &setup,
+        solcircle_arcium::instruction::CancelAuthorityTransfer {},
+    );
+    send(&mut setup.context, &[&setup.authority], &[ix])
+        .await
+        .unwrap();
+
+    setup.context.warp_to_slot(past_timelock()).unwrap();
+    let ix = accept_authority_ix(&new_authority.pubkey());
+    let result = send(
+        &mut setup.context,
+        &[&setup.authority, &new_authority],
+        &[ix],
+    )
+    .await;
+    assert_error(result, 0, ErrorCode::Unauthorized);
+    let authority = setup.authority.pubkey();
+    assert_eq!(get_config(&mut setup).await.authority, authority);
+}
@@ -0,0 +1,281 @@
+// Oracle signature verification tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, mint_account, oracle_config, pda, send, signed_by, token_account, SetupBuilder,
+};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+use solcircle_arcium::{
+    ErrorCode, OracleAction, OracleAmount, OracleAuthorization, OracleCluster, UserDeposit,
+    ORACLE_DOMAIN,
+};
+
+const DEPOSIT: u64 = 1_000;
+const NEXT_NONCE: u64 = 3;
+const EXPIRY_SLOT: u64 = 1_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    user: Keypair,
+    oracles: Vec<Keypair>,
+    mint: Pubkey,
+    user_token_account: Pubkey,
+    other_mint_token_account: Pubkey,
+}
+
+async fn setup() -> Setup {
+    setup_with_oracles(1, 1).await
+}
+
+async fn setup_with_oracles(num_oracles: usize, threshold: u8) -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let user = Keypair::new();
+    let oracles: Vec<Keypair> = (0..num_oracles).map(|_| Keypair::new()).collect();
+    let mint = Pubkey::new_unique();
+    let user_token_account = Pubkey::new_unique();
+    let other_mint_token_account = Pubkey::new_unique();
+
+    let (user_deposit, deposit_bump) =
+        pda(&[b"user_deposit", user.pubkey().as_ref(), mint.as_ref()]);
+    let (user_vault, vault_bump) = pda(&[b"user_vault", user.pubkey().as_ref(), mint.as_ref()]);
+
+    builder.oracle_config(oracle_config(
+        Pubkey::new_unique(),
+        oracles.iter().map(|oracle| oracle.pubkey()).collect(),
+        threshold,
+    ));
+    builder.program_account(
+        user_deposit,
+        &UserDeposit {
+            owner: user.pubkey(),
+            mint,
+            deposited_amount: DEPOSIT,
+            oracle_nonce: NEXT_NONCE,
+            bump: deposit_bump,
+            vault_bump,
+        },
+    );
+    builder.account(mint, mint_account());
+    builder.account(user_vault, token_account(mint, user_vault, DEPOSIT));
+    builder.account(user_token_account, token_account(mint, user.pubkey(), 0));
+    builder.account(
+        other_mint_token_account,
+        token_account(Pubkey::new_unique(), user.pubkey(), 0),
+    );
+    builder.wallet(user.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        user,
+        oracles,
+        mint,
+        user_token_account,
+        other_mint_token_account,
+    }
+}
+
+fn withdraw_message(setup: &Setup, user: &Pubkey, amount: u64, nonce: u64) -> Vec<u8> {
+    let authorization = OracleAuthorization {
+        domain: ORACLE_DOMAIN,
+        program_id: solcircle_arcium::ID,
+        cluster: OracleCluster::Localnet,
+        action: OracleAction::Withdraw,
+        user: *user,
+        group: Pubkey::default(),
+        mint: setup.mint,
+        amount: OracleAmount::Plain(amount),
+        nonce,
+        expiry_slot: EXPIRY_SLOT,
+    };
+    let mut message = Vec::new();
+    authorization.serialize(&mut message).unwrap();
+    message
+}
+
+fn withdraw_ix(setup: &Setup, amount: u64, nonce: u64) -> Instruction {
+    withdraw_to_ix(setup, setup.user_token_account, amount, nonce)
+}
+
+fn withdraw_to_ix(
+    setup: &Setup,
+    user_token_account: Pubkey,
+    amount: u64,
+    nonce: u64,
+) -> Instruction {
+    let user = setup.user.pubkey();
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::WithdrawFromUserPda {
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            user_token_account,
+            user,
+            owner: user,
+            oracle_config: pda(&[b"oracle_config"]).0,
+            instructions_sysvar: sysvar::instructions::ID,
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::WithdrawFromUserPda {
+            amount,
+            nonce,
+            expiry_slot: EXPIRY_SLOT,
+        }
+        .data(),
+    }
+}
+
+fn assert_invalid_oracle_signature(result: Result<(), BanksClientError>, ix_index: u8) {
+    assert_error(result, ix_index, ErrorCode::InvalidOracleSignature);
+}
+
+#[tokio::test]
+async fn rejects_withdraw_without_ed25519_instruction() {
+    let mut setup = setup().await;
+
+    let ix = withdraw_ix(&setup, 100, NEXT_NONCE);
+    let result = send(&mut setup.context, &[&setup.user], &[ix]).await;
+
+    assert_invalid_oracle_signature(result, 0);
+}
+
+#[tokio::test]
+async fn rejects_signature_from_wrong_key() {
+    let mut setup = setup().await;
+    let user = setup.user.pubkey();
+    let impostor = Keypair::new();
+
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&impostor, &message),
+        withdraw_ix(&setup, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_invalid_oracle_signature(result, 1);
+}
+
+#[tokio::test]
+async fn rejects_oracle_signature_replayed_for_different_amount() {
+    let mut setup = setup().await;
+    let user = setup.user.pubkey();
+
+    // A genuine authorization for 100 can't be reused to pull 500
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 500, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_invalid_oracle_signature(result, 1);
+}
+
+#[tokio::test]
+async fn rejects_oracle_signature_replayed_for_different_user() {
+    let mut setup = setup().await;
+    let other_user = Pubkey::new_unique();
+
+    let message = withdraw_message(&setup, &other_user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_invalid_oracle_signature(result, 1);
+}
+
+#[tokio::test]
+async fn rejects_already_used_nonce() {
+    let mut setup = setup().await;
+    let user = setup.user.pubkey();
+
+    // Correctly signed by the oracle, but for a nonce the account has moved past
+    let stale_nonce = NEXT_NONCE - 1;
+    let message = withdraw_message(&setup, &user, 100, stale_nonce);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 100, stale_nonce),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_error(result, 1, ErrorCode::StaleOracleNonce);
+}
+
+#[tokio::test]
+async fn rejects_expired_authorization() {
+    let mut setup = setup().await;
+    let user = setup.user.pubkey();
+
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    setup.context.warp_to_slot(EXPIRY_SLOT + 1).unwrap();
+
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_error(result, 1, ErrorCode::OracleAuthorizationExpired);
+}
+
+#[tokio::test]
+async fn rejects_fewer_signatures_than_threshold() {
+    let mut setup = setup_with_oracles(3, 2).await;
+    let user = setup.user.pubkey();
+
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_invalid_oracle_signature(result, 1);
+}
+
+#[tokio::test]
+async fn rejects_same_oracle_counted_twice() {
+    let mut setup = setup_with_oracles(3, 2).await;
+    let user = setup.user.pubkey();
+
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        signed_by(&setup.oracles[0], &message),
+        withdraw_ix(&setup, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_invalid_oracle_signature(result, 2);
+}
+
+#[tokio::test]
+async fn rejects_token_account_for_another_mint() {
+    let mut setup = setup().await;
+    let user = setup.user.pubkey();
+
+    let message = withdraw_message(&setup, &user, 100, NEXT_NONCE);
+    let ixs = [
+        signed_by(&setup.oracles[0], &message),
+        withdraw_to_ix(&setup, setup.other_mint_token_account, 100, NEXT_NONCE),
+    ];
+    let result = send(&mut setup.context, &[&setup.user], &ixs).await;
+
+    assert_error(result, 1, ErrorCode::MintMismatch);
+}
@@ -0,0 +1,558 @@
+// Pool swap execution tests, routed through the mock AMM
+//
+// Loads the compiled programs, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, fetch, mint_account, pda, send, token_account, token_balance, SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{
+    ErrorCode, GroupPool, MemberPosition, Proposal, ProposalAction, ProposalStatus, Session,
+    SessionStatus, SwapAllowlist, UserDeposit,
+};
+
+const GROUP_ID: &str = "circle";
+const POOL_BALANCE: u64 = 3_000;
+const BUY_AMOUNT: u64 = 1_000;
+const SELL_AMOUNT: u64 = 300;
+const RESERVE: u64 = 10_000;
+const PROPOSAL_ID: u64 = 1;
+const SELL_PROPOSAL_ID: u64 = 2;
+const WRITE_OFF_PROPOSAL_ID: u64 = 3;
+
+struct Setup {
+    context: ProgramTestContext,
+    admin: Keypair,
+    // Only member, holding every share
+    alice: Pubkey,
+    mint: Pubkey,
+    token_mint: Pubkey,
+    group_pool: Pubkey,
+    reserve_in: Pubkey,
+    reserve_out: Pubkey,
+    // Another token account the group vault owns
+    other_vault: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+    builder.program("mock_amm", mock_amm::ID);
+
+    let admin = Keypair::new();
+    let alice = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let token_mint = Pubkey::new_unique();
+    let (group_pool, pool_bump) = pda(&[b"group_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (group_vault, group_vault_bump) =
+        pda(&[b"group_vault", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (session, session_bump) = pda(&[b"session", group_pool.as_ref()]);
+    let (proposal, proposal_bump) =
+        pda(&[b"proposal", group_pool.as_ref(), &PROPOSAL_ID.to_le_bytes()]);
+    let (sell_proposal, sell_proposal_bump) = pda(&[
+        b"proposal",
+        group_pool.as_ref(),
+        &SELL_PROPOSAL_ID.to_le_bytes(),
+    ]);
+    let (write_off_proposal, write_off_proposal_bump) = pda(&[
+        b"proposal",
+        group_pool.as_ref(),
+        &WRITE_OFF_PROPOSAL_ID.to_le_bytes(),
+    ]);
+    let (member_position, position_bump) =
+        pda(&[b"member_position", group_pool.as_ref(), alice.as_ref()]);
+    let (user_deposit, deposit_bump) = pda(&[b"user_deposit", alice.as_ref(), mint.as_ref()]);
+    let (user_vault, vault_bump) = pda(&[b"user_vault", alice.as_ref(), mint.as_ref()]);
+    let (swap_allowlist, allowlist_bump) = pda(&[b"swap_allowlist"]);
+    let reserve_authority = reserve_authority();
+    let reserve_in = Pubkey::new_unique();
+    let reserve_out = Pubkey::new_unique();
+    let other_vault = Pubkey::new_unique();
+
+    builder.account(mint, mint_account());
+    builder.account(token_mint, mint_account());
+    builder.program_account(
+        group_pool,
+        &GroupPool {
+            admin: admin.pubkey(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Circle".to_string(),
+            total_locked: POOL_BALANCE,
+            total_shares: POOL_BALANCE,
+            net_asset_value: POOL_BALANCE,
+            is_active: true,
+            open_positions: vec![],
+            bump: pool_bump,
+            vault_bump: group_vault_bump,
+        },
+    );
+    builder.account(group_vault, token_account(mint, group_vault, POOL_BALANCE));
+    builder.program_account(
+        session,
+        &Session {
+            pool: group_pool,
+            session_id: 0,
+            start_slot: 0,
+            end_slot: u64::MAX,
+            status: SessionStatus::Trading,
+            bump: session_bump,
+        },
+    );
+    for (address, proposal_id, action, amount, bump) in [
+        (
+            proposal,
+            PROPOSAL_ID,
+            ProposalAction::Buy,
+            BUY_AMOUNT,
+            proposal_bump,
+        ),
+        (
+            sell_proposal,
+            SELL_PROPOSAL_ID,
+            ProposalAction::Sell,
+            SELL_AMOUNT,
+            sell_proposal_bump,
+        ),
+        (
+            write_off_proposal,
+            WRITE_OFF_PROPOSAL_ID,
+            ProposalAction::WriteOff,
+            SELL_AMOUNT,
+            write_off_proposal_bump,
+        ),
+    ] {
+        builder.program_account(
+            address,
+            &Proposal {
+                pool: group_pool,
+                proposal_id,
+                proposer: admin.pubkey(),
+                action,
+                token: token_mint,
+                amount,
+                recipient: Pubkey::default(),
+                created_slot: 0,
+                deadline_slot: 0,
+                quorum_bps: 5_000,
+                total_weight: POOL_BALANCE,
+                yes_weight: POOL_BALANCE,
+                no_weight: 0,
+                status: ProposalStatus::Passed,
+                bump,
+            },
+        );
+    }
+    builder.program_account(
+        swap_allowlist,
+        &SwapAllowlist {
+            programs: vec![mock_amm::ID],
+            bump: allowlist_bump,
+        },
+    );
+    builder.program_account(
+        member_position,
+        &MemberPosition {
+            pool: group_pool,
+            member: alice,
+            contributed_amount: POOL_BALANCE,
+            shares: POOL_BALANCE,
+            last_increase_slot: 0,
+            bump: position_bump,
+        },
+    );
+    builder.program_account(
+        user_deposit,
+        &UserDeposit {
+            owner: alice,
+            mint,
+            deposited_amount: 0,
+            oracle_nonce: 0,
+            bump: deposit_bump,
+            vault_bump,
+        },
+    );
+    builder.account(user_vault, token_account(mint, user_vault, 0));
+    builder.account(other_vault, token_account(token_mint, group_vault, 0));
+    builder.account(reserve_in, token_account(mint, reserve_authority, 0));
+    builder.account(
+        reserve_out,
+        token_account(token_mint, reserve_authority, RESERVE),
+    );
+    builder.wallet(admin.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        admin,
+        alice,
+        mint,
+        token_mint,
+        group_pool,
+        reserve_in,
+        reserve_out,
+        other_vault,
+    }
+}
+
+fn reserve_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"reserve_authority"], &mock_amm::ID).0
+}
+
+fn group_vault(setup: &Setup) -> Pubkey {
+    pda(&[b"group_vault", GROUP_ID.as_bytes(), setup.mint.as_ref()]).0
+}
+
+fn token_vault(setup: &Setup) -> Pubkey {
+    pda(&[
+        b"pool_token_vault",
+        setup.group_pool.as_ref(),
+        setup.token_mint.as_ref(),
+    ])
+    .0
+}
+
+fn swap_ix(
+    setup: &Setup,
+    swap_program: Pubkey,
+    amount_out: u64,
+    min_amount_out: u64,
+) -> Instruction {
+    trade_ix(
+        setup,
+        swap_program,
+        ProposalAction::Buy,
+        amount_out,
+        min_amount_out,
+    )
+}
+
+fn trade_ix(
+    setup: &Setup,
+    swap_program: Pubkey,
+    action: ProposalAction,
+    amount_out: u64,
+    min_amount_out: u64,
+) -> Instruction {
+    let (proposal_id, amount_in) = match action {
+        ProposalAction::Sell => (SELL_PROPOSAL_ID, SELL_AMOUNT),
+        _ => (PROPOSAL_ID, BUY_AMOUNT),
+    };
+    let mut accounts = solcircle_arcium::accounts::ExecutePoolSwap {
+        proposal: pda(&[
+            b"proposal",
+            setup.group_pool.as_ref(),
+            &proposal_id.to_le_bytes(),
+        ])
+        .0,
+        group_pool: setup.group_pool,
+        group_vault: group_vault(setup),
+        token_vault: token_vault(setup),
+        session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+        swap_allowlist: pda(&[b"swap_allowlist"]).0,
+        swap_program,
+        token_mint: setup.token_mint,
+        admin: setup.admin.pubkey(),
+        token_program: spl_token::ID,
+        system_program: solana_sdk::system_program::ID,
+    }
+    .to_account_metas(None);
+
+    // The AMM's own accounts; the pool signs for the group vault itself.
+    // A sell runs the same pair of reserves the other way.
+    let amm_accounts = if action == ProposalAction::Sell {
+        mock_amm::accounts::Swap {
+            user_authority: group_vault(setup),
+            user_source: token_vault(setup),
+            user_destination: group_vault(setup),
+            reserve_in: setup.reserve_out,
+            reserve_out: setup.reserve_in,
+            reserve_authority: reserve_authority(),
+            mint_in: setup.token_mint,
+            mint_out: setup.mint,
+            token_program: spl_token::ID,
+        }
+    } else {
+        mock_amm::accounts::Swap {
+            user_authority: group_vault(setup),
+            user_source: group_vault(setup),
+            user_destination: token_vault(setup),
+            reserve_in: setup.reserve_in,
+            reserve_out: setup.reserve_out,
+            reserve_authority: reserve_authority(),
+            mint_in: setup.mint,
+            mint_out: setup.token_mint,
+            token_program: spl_token::ID,
+        }
+    }
+    .to_account_metas(None);
+    accounts.extend(amm_accounts.into_iter().map(|mut meta| {
+        meta.is_signer = false;
+        meta
+    }));
+
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts,
+        data: solcircle_arcium::instruction::ExecutePoolSwap {
+            min_amount_out,
+            swap_data: mock_amm::instruction::Swap {
+                amount_in,
+                amount_out,
+            }
+            .data(),
+        }
+        .data(),
+    }
+}
+
+#[tokio::test]
+async fn buys_into_per_mint_vault_through_allowlisted_amm() {
+    let mut setup = setup().await;
+
+    let ix = swap_ix(&setup, mock_amm::ID, 300, 300);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+
+    let vault = group_vault(&setup);
+    assert_eq!(
+        token_balance(&mut setup.context, vault).await,
+        POOL_BALANCE - BUY_AMOUNT
+    );
+    let vault = token_vault(&setup);
+    assert_eq!(token_balance(&mut setup.context, vault).await, 300);
+
+    let address = pda(&[
+        b"proposal",
+        setup.group_pool.as_ref(),
+        &PROPOSAL_ID.to_le_bytes(),
+    ])
+    .0;
+    let proposal: Proposal = fetch(&mut setup.context, address).await;
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+}
+
+#[tokio::test]
+async fn rejects_swap_below_min_out() {
+    let mut setup = setup().await;
+
+    let ix = swap_ix(&setup, mock_amm::ID, 250, 300);
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::SlippageExceeded);
+}
+
+#[tokio::test]
+async fn rejects_program_off_allowlist() {
+    let mut setup = setup().await;
+
+    // Executable, but never allowed by the authority
+    let ix = swap_ix(&setup, spl_token::ID, 300, 300);
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::SwapProgramNotAllowed);
+}
+
+fn write_off_ix(setup: &Setup) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::WriteOffPosition {
+            proposal: pda(&[
+                b"proposal",
+                setup.group_pool.as_ref(),
+                &WRITE_OFF_PROPOSAL_ID.to_le_bytes(),
+            ])
+            .0,
+            group_pool: setup.group_pool,
+            admin: setup.admin.pubkey(),
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::WriteOffPosition {}.data(),
+    }
+}
+
+fn advance_ix(setup: &Setup) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::AdvanceSession {
+            group_pool: setup.group_pool,
+            session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+            caller: setup.admin.pubkey(),
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::AdvanceSession {}.data(),
+    }
+}
+
+fn claim_ix(setup: &Setup) -> Instruction {
+    let member = setup.alice;
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ClaimSessionFunds {
+            group_pool: setup.group_pool,
+            group_vault: group_vault(setup),
+            session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+            member_position: pda(&[
+                b"member_position",
+                setup.group_pool.as_ref(),
+                member.as_ref(),
+            ])
+            .0,
+            user_deposit: pda(&[b"user_deposit", member.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", member.as_ref(), setup.mint.as_ref()]).0,
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ClaimSessionFunds {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn holds_close_until_bought_tokens_are_sold() {
+    let mut setup = setup().await;
+
+    let ix = swap_ix(&setup, mock_amm::ID, 300, 300);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let token_mint = setup.token_mint;
+    assert_eq!(
+        fetch::<GroupPool>(&mut setup.context, setup.group_pool)
+            .await
+            .open_positions,
+        vec![token_mint]
+    );
+
+    // Trading -> Settling, then Closed is refused while tokens are held
+    let ix = advance_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let ix = advance_ix(&setup);
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+    assert_error(result, 0, ErrorCode::PoolHasOpenPositions);
+
+    let ix = trade_ix(&setup, mock_amm::ID, ProposalAction::Sell, 1_000, 1_000);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert!(fetch::<GroupPool>(&mut setup.context, setup.group_pool)
+        .await
+        .open_positions
+        .is_empty());
+
+    let ix = advance_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let ix = claim_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+
+    // Alice owns every share, so she takes everything the trade returned
+    let user_vault = pda(&[b"user_vault", setup.alice.as_ref(), setup.mint.as_ref()]).0;
+    assert_eq!(
+        token_balance(&mut setup.context, user_vault).await,
+        POOL_BALANCE
+    );
+    let vault = group_vault(&setup);
+    assert_eq!(token_balance(&mut setup.context, vault).await, 0);
+    let vault = token_vault(&setup);
+    assert_eq!(token_balance(&mut setup.context, vault).await, 0);
+}
+
+#[tokio::test]
+async fn rejects_other_group_vault_accounts_in_swap() {
+    let mut setup = setup().await;
+
+    // The group vault's signature would also cover this account
+    let mut ix = swap_ix(&setup, mock_amm::ID, 300, 300);
+    ix.accounts.push(AccountMeta::new(setup.other_vault, false));
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::SwapAccountNotAllowed);
+}
+
+#[tokio::test]
+async fn write_off_releases_a_pool_stuck_on_an_unsellable_token() {
+    let mut setup = setup().await;
+
+    let ix = swap_ix(&setup, mock_amm::ID, 300, 300);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let ix = advance_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+
+    // Nothing will buy the token back, so the vote writes it off
+    let ix = write_off_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert!(fetch::<GroupPool>(&mut setup.context, setup.group_pool)
+        .await
+        .open_positions
+        .is_empty());
+
+    let ix = advance_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let ix = claim_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+
+    // Alice gets what is left of the pool's mint; the tokens stay behind
+    let user_vault = pda(&[b"user_vault", setup.alice.as_ref(), setup.mint.as_ref()]).0;
+    assert_eq!(
+        token_balance(&mut setup.context, user_vault).await,
+        POOL_BALANCE - BUY_AMOUNT
+    );
+    let vault = token_vault(&setup);
+    assert_eq!(token_balance(&mut setup.context, vault).await, 300);
+}
+
+#[tokio::test]
+async fn rejects_write_off_of_token_not_held() {
+    let mut setup = setup().await;
+
+    let ix = write_off_ix(&setup);
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::PositionNotOpen);
+}
+
+#[tokio::test]
+async fn rejects_swap_that_delegates_a_vault() {
+    let mut setup = setup().await;
+
+    // Trades at the agreed rate, but also approves a delegate over the
+    // group vault with the pool's signature
+    let mut ix = swap_ix(&setup, mock_amm::ID, 300, 300);
+    ix.data = solcircle_arcium::instruction::ExecutePoolSwap {
+        min_amount_out: 300,
+        swap_data: mock_amm::instruction::SwapAndApprove {
+            amount_in: BUY_AMOUNT,
+            amount_out: 300,
+        }
+        .data(),
+    }
+    .data();
+    let result = send(&mut setup.context, &[&setup.admin], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::SwapVaultAltered);
+}
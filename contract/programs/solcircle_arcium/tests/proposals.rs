@@ -0,0 +1,338 @@
+// Proposal and weighted voting tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{assert_error, fetch, mint_account, pda, send, token_account, SetupBuilder};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{
+    ErrorCode, GroupPool, MemberPosition, Proposal, ProposalAction, ProposalStatus, Session,
+    SessionStatus, UserDeposit,
+};
+
+const GROUP_ID: &str = "circle";
+const ALICE_LOCKED: u64 = 2_000;
+const BOB_LOCKED: u64 = 1_000;
+const RELEASE: u64 = 500;
+const PROPOSAL_ID: u64 = 1;
+const START_SLOT: u64 = 10;
+const VOTING_SLOTS: u64 = 50;
+
+struct Setup {
+    context: ProgramTestContext,
+    alice: Keypair,
+    bob: Keypair,
+    mint: Pubkey,
+    group_pool: Pubkey,
+}
+
+async fn setup() -> Setup {
+    setup_with_bob_topped_up_at(0).await
+}
+
+async fn setup_with_bob_topped_up_at(bob_last_increase_slot: u64) -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let (group_pool, pool_bump) = pda(&[b"group_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (group_vault, group_vault_bump) =
+        pda(&[b"group_vault", GROUP_ID.as_bytes(), mint.as_ref()]);
+
+    builder.account(mint, mint_account()).program_account(
+        group_pool,
+        &GroupPool {
+            admin: Pubkey::new_unique(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Circle".to_string(),
+            total_locked: ALICE_LOCKED + BOB_LOCKED,
+            total_shares: ALICE_LOCKED + BOB_LOCKED,
+            net_asset_value: ALICE_LOCKED + BOB_LOCKED,
+            is_active: true,
+            open_positions: vec![],
+            bump: pool_bump,
+            vault_bump: group_vault_bump,
+        },
+    );
+    builder.account(
+        group_vault,
+        token_account(mint, group_vault, ALICE_LOCKED + BOB_LOCKED),
+    );
+    let (session, session_bump) = pda(&[b"session", group_pool.as_ref()]);
+    builder.program_account(
+        session,
+        &Session {
+            pool: group_pool,
+            session_id: 0,
+            start_slot: 0,
+            end_slot: 1,
+            status: SessionStatus::Closed,
+            bump: session_bump,
+        },
+    );
+
+    for (member, locked, last_increase_slot) in [
+        (&alice, ALICE_LOCKED, 0),
+        (&bob, BOB_LOCKED, bob_last_increase_slot),
+    ] {
+        let member = member.pubkey();
+        let (position, position_bump) =
+            pda(&[b"member_position", group_pool.as_ref(), member.as_ref()]);
+        let (user_deposit, deposit_bump) = pda(&[b"user_deposit", member.as_ref(), mint.as_ref()]);
+        let (user_vault, vault_bump) = pda(&[b"user_vault", member.as_ref(), mint.as_ref()]);
+
+        builder.program_account(
+            position,
+            &MemberPosition {
+                pool: group_pool,
+                member,
+                contributed_amount: locked,
+                shares: locked,
+                last_increase_slot,
+                bump: position_bump,
+            },
+        );
+        builder.program_account(
+            user_deposit,
+            &UserDeposit {
+                owner: member,
+                mint,
+                deposited_amount: 0,
+                oracle_nonce: 0,
+                bump: deposit_bump,
+                vault_bump,
+            },
+        );
+        builder
+            .account(user_vault, token_account(mint, user_vault, 0))
+            .wallet(member);
+    }
+    builder.start_at(START_SLOT);
+
+    Setup {
+        context: builder.start().await,
+        alice,
+        bob,
+        mint,
+        group_pool,
+    }
+}
+
+fn proposal_address(setup: &Setup) -> Pubkey {
+    pda(&[
+        b"proposal",
+        setup.group_pool.as_ref(),
+        &PROPOSAL_ID.to_le_bytes(),
+    ])
+    .0
+}
+
+fn member_position(setup: &Setup, member: &Pubkey) -> Pubkey {
+    pda(&[
+        b"member_position",
+        setup.group_pool.as_ref(),
+        member.as_ref(),
+    ])
+    .0
+}
+
+fn create_proposal_ix(setup: &Setup) -> Instruction {
+    let proposer = setup.alice.pubkey();
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::CreateProposal {
+            group_pool: setup.group_pool,
+            member_position: member_position(setup, &proposer),
+            proposal: proposal_address(setup),
+            proposer,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::CreateProposal {
+            proposal_id: PROPOSAL_ID,
+            action: ProposalAction::Release,
+            token: setup.mint,
+            amount: RELEASE,
+            recipient: proposer,
+            voting_slots: VOTING_SLOTS,
+            quorum_bps: 5_000,
+        }
+        .data(),
+    }
+}
+
+fn vote_ix(setup: &Setup, voter: &Pubkey, approve: bool) -> Instruction {
+    let proposal = proposal_address(setup);
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::CastVote {
+            proposal,
+            member_position: member_position(setup, voter),
+            vote_record: pda(&[b"vote", proposal.as_ref(), voter.as_ref()]).0,
+            voter: *voter,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::CastVote { approve }.data(),
+    }
+}
+
+fn finalize_ix(setup: &Setup) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::FinalizeProposal {
+            proposal: proposal_address(setup),
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::FinalizeProposal {}.data(),
+    }
+}
+
+fn execute_ix(setup: &Setup) -> Instruction {
+    let recipient = setup.alice.pubkey();
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ExecuteReleaseProposal {
+            proposal: proposal_address(setup),
+            group_pool: setup.group_pool,
+            group_vault: pda(&[b"group_vault", GROUP_ID.as_bytes(), setup.mint.as_ref()]).0,
+            session: pda(&[b"session", setup.group_pool.as_ref()]).0,
+            user_deposit: pda(&[b"user_deposit", recipient.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", recipient.as_ref(), setup.mint.as_ref()]).0,
+            member_position: member_position(setup, &recipient),
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ExecuteReleaseProposal {}.data(),
+    }
+}
+
+async fn get_proposal(setup: &mut Setup) -> Proposal {
+    let proposal = proposal_address(setup);
+    fetch(&mut setup.context, proposal).await
+}
+
+#[tokio::test]
+async fn weighted_majority_passes_and_executes_release() {
+    let mut setup = setup().await;
+
+    let ix = create_proposal_ix(&setup);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    let ix = vote_ix(&setup, &setup.alice.pubkey(), true);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    let ix = vote_ix(&setup, &setup.bob.pubkey(), false);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+
+    let proposal = get_proposal(&mut setup).await;
+    assert_eq!(proposal.yes_weight, ALICE_LOCKED);
+    assert_eq!(proposal.no_weight, BOB_LOCKED);
+
+    setup
+        .context
+        .warp_to_slot(START_SLOT + VOTING_SLOTS + 1)
+        .unwrap();
+    let ix = finalize_ix(&setup);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_proposal(&mut setup).await.status,
+        ProposalStatus::Passed
+    );
+
+    // No oracle signature: the passed proposal is the authorization
+    let ix = execute_ix(&setup);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_proposal(&mut setup).await.status,
+        ProposalStatus::Executed
+    );
+
+    let pool: GroupPool = fetch(&mut setup.context, setup.group_pool).await;
+    assert_eq!(pool.total_locked, ALICE_LOCKED + BOB_LOCKED - RELEASE);
+}
+
+#[tokio::test]
+async fn rejects_second_vote_from_same_member() {
+    let mut setup = setup().await;
+
+    let ix = create_proposal_ix(&setup);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    let ix = vote_ix(&setup, &setup.alice.pubkey(), true);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+
+    let ix = vote_ix(&setup, &setup.alice.pubkey(), false);
+    assert!(send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .is_err());
+    assert_eq!(get_proposal(&mut setup).await.no_weight, 0);
+}
+
+#[tokio::test]
+async fn rejects_execution_before_proposal_passes() {
+    let mut setup = setup().await;
+
+    let ix = create_proposal_ix(&setup);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    let ix = vote_ix(&setup, &setup.alice.pubkey(), true);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+
+    let ix = execute_ix(&setup);
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::ProposalNotPassed);
+}
+
+#[tokio::test]
+async fn rejects_vote_from_position_topped_up_after_proposal_opened() {
+    // Bob's release landed after the proposal snapshotted `total_weight`
+    let topped_up_slot = START_SLOT + VOTING_SLOTS / 2;
+    let mut setup = setup_with_bob_topped_up_at(topped_up_slot).await;
+
+    let ix = create_proposal_ix(&setup);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    setup.context.warp_to_slot(topped_up_slot + 1).unwrap();
+
+    let ix = vote_ix(&setup, &setup.bob.pubkey(), false);
+    let result = send(&mut setup.context, &[&setup.bob], &[ix]).await;
+    assert_error(result, 0, ErrorCode::VotingWeightChanged);
+
+    // Positions untouched since the snapshot still vote
+    let ix = vote_ix(&setup, &setup.alice.pubkey(), true);
+    send(&mut setup.context, &[&setup.alice], &[ix])
+        .await
+        .unwrap();
+    let proposal = get_proposal(&mut setup).await;
+    assert_eq!(proposal.yes_weight, ALICE_LOCKED);
+    assert_eq!(proposal.no_weight, 0);
+}
@@ -0,0 +1,320 @@
+// Trading session lifecycle tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use common::{
+    assert_error, fetch, mint_account, oracle_config, pda, send, token_account, SetupBuilder,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+use solcircle_arcium::{ErrorCode, GroupPool, MemberPosition, Session, SessionStatus, UserDeposit};
+
+const GROUP_ID: &str = "circle";
+const ALICE_LOCKED: u64 = 2_000;
+const BOB_LOCKED: u64 = 1_000;
+// The pool made 50% while trading
+const VAULT_BALANCE: u64 = 4_500;
+const START_SLOT: u64 = 10;
+const SESSION_START: u64 = 20;
+const SESSION_END: u64 = 40;
+
+struct Setup {
+    context: ProgramTestContext,
+    admin: Keypair,
+    alice: Keypair,
+    bob: Keypair,
+    mint: Pubkey,
+    group_pool: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let admin = Keypair::new();
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let (group_pool, pool_bump) = pda(&[b"group_pool", GROUP_ID.as_bytes(), mint.as_ref()]);
+    let (group_vault, group_vault_bump) =
+        pda(&[b"group_vault", GROUP_ID.as_bytes(), mint.as_ref()]);
+
+    builder.account(mint, mint_account());
+    builder.program_account(
+        group_pool,
+        &GroupPool {
+            admin: admin.pubkey(),
+            mint,
+            group_id: GROUP_ID.to_string(),
+            group_name: "Circle".to_string(),
+            total_locked: ALICE_LOCKED + BOB_LOCKED,
+            total_shares: ALICE_LOCKED + BOB_LOCKED,
+            net_asset_value: VAULT_BALANCE,
+            is_active: true,
+            open_positions: vec![],
+            bump: pool_bump,
+            vault_bump: group_vault_bump,
+        },
+    );
+    builder.account(group_vault, token_account(mint, group_vault, VAULT_BALANCE));
+    builder.oracle_config(oracle_config(
+        Pubkey::new_unique(),
+        vec![Pubkey::new_unique()],
+        1,
+    ));
+
+    for (member, locked) in [(&alice, ALICE_LOCKED), (&bob, BOB_LOCKED)] {
+        let member = member.pubkey();
+        let (position, position_bump) =
+            pda(&[b"member_position", group_pool.as_ref(), member.as_ref()]);
+        let (user_deposit, deposit_bump) = pda(&[b"user_deposit", member.as_ref(), mint.as_ref()]);
+        let (user_vault, vault_bump) = pda(&[b"user_vault", member.as_ref(), mint.as_ref()]);
+
+        builder.program_account(
+            position,
+            &MemberPosition {
+                pool: group_pool,
+                member,
+                contributed_amount: locked,
+                shares: locked,
+                last_increase_slot: 0,
+                bump: position_bump,
+            },
+        );
+        builder.program_account(
+            user_deposit,
+            &UserDeposit {
+                owner: member,
+                mint,
+                deposited_amount: locked,
+                oracle_nonce: 0,
+                bump: deposit_bump,
+                vault_bump,
+            },
+        );
+        builder.account(user_vault, token_account(mint, user_vault, locked));
+    }
+    for signer in [&admin, &alice, &bob] {
+        builder.wallet(signer.pubkey());
+    }
+    builder.start_at(START_SLOT);
+
+    Setup {
+        context: builder.start().await,
+        admin,
+        alice,
+        bob,
+        mint,
+        group_pool,
+    }
+}
+
+fn session_address(setup: &Setup) -> Pubkey {
+    pda(&[b"session", setup.group_pool.as_ref()]).0
+}
+
+fn group_vault(setup: &Setup) -> Pubkey {
+    pda(&[b"group_vault", GROUP_ID.as_bytes(), setup.mint.as_ref()]).0
+}
+
+fn start_ix(setup: &Setup) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::StartSession {
+            group_pool: setup.group_pool,
+            session: session_address(setup),
+            admin: setup.admin.pubkey(),
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::StartSession {
+            start_slot: SESSION_START,
+            end_slot: SESSION_END,
+        }
+        .data(),
+    }
+}
+
+fn advance_ix(setup: &Setup, caller: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::AdvanceSession {
+            group_pool: setup.group_pool,
+            session: session_address(setup),
+            caller: *caller,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::AdvanceSession {}.data(),
+    }
+}
+
+fn claim_ix(setup: &Setup, member: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ClaimSessionFunds {
+            group_pool: setup.group_pool,
+            group_vault: group_vault(setup),
+            session: session_address(setup),
+            member_position: pda(&[
+                b"member_position",
+                setup.group_pool.as_ref(),
+                member.as_ref(),
+            ])
+            .0,
+            user_deposit: pda(&[b"user_deposit", member.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", member.as_ref(), setup.mint.as_ref()]).0,
+            mint: setup.mint,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ClaimSessionFunds {}.data(),
+    }
+}
+
+async fn get_session(setup: &mut Setup) -> Session {
+    let session = session_address(setup);
+    fetch(&mut setup.context, session).await
+}
+
+async fn deposited_amount(setup: &mut Setup, member: &Pubkey) -> u64 {
+    let address = pda(&[b"user_deposit", member.as_ref(), setup.mint.as_ref()]).0;
+    fetch::<UserDeposit>(&mut setup.context, address)
+        .await
+        .deposited_amount
+}
+
+#[tokio::test]
+async fn session_runs_to_close_and_pays_pro_rata_claims() {
+    let mut setup = setup().await;
+    let admin = setup.admin.pubkey();
+    let alice = setup.alice.pubkey();
+    let bob = setup.bob.pubkey();
+
+    let ix = start_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_session(&mut setup).await.status, SessionStatus::Open);
+
+    // Only the admin can lock before `start_slot`
+    let ix = advance_ix(&setup, &bob);
+    let result = send(&mut setup.context, &[&setup.bob], &[ix]).await;
+    assert_error(result, 0, ErrorCode::SessionTransitionNotAllowed);
+
+    setup.context.warp_to_slot(SESSION_START).unwrap();
+    let ix = advance_ix(&setup, &bob);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_session(&mut setup).await.status, SessionStatus::Locked);
+
+    let ix = advance_ix(&setup, &admin);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_session(&mut setup).await.status, SessionStatus::Trading);
+
+    let ix = claim_ix(&setup, &alice);
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+    assert_error(result, 0, ErrorCode::SessionNotClosed);
+
+    setup.context.warp_to_slot(SESSION_END).unwrap();
+    let ix = advance_ix(&setup, &bob);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+    let ix = advance_ix(&setup, &admin);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_session(&mut setup).await.status, SessionStatus::Closed);
+
+    // Anyone can crank a claim; each member gets their share of the gains
+    let ix = claim_ix(&setup, &alice);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+    let ix = claim_ix(&setup, &bob);
+    send(&mut setup.context, &[&setup.bob], &[ix])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        deposited_amount(&mut setup, &alice).await,
+        ALICE_LOCKED + 3_000
+    );
+    assert_eq!(deposited_amount(&mut setup, &bob).await, BOB_LOCKED + 1_500);
+}
+
+#[tokio::test]
+async fn rejects_claim_while_session_open() {
+    let mut setup = setup().await;
+    let alice = setup.alice.pubkey();
+
+    let ix = start_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    assert_eq!(get_session(&mut setup).await.status, SessionStatus::Open);
+
+    // Funds can leave an open session, but only by release, not by claim
+    let ix = claim_ix(&setup, &alice);
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+    assert_error(result, 0, ErrorCode::SessionNotClosed);
+    assert_eq!(deposited_amount(&mut setup, &alice).await, ALICE_LOCKED);
+}
+
+#[tokio::test]
+async fn rejects_release_into_locked_session() {
+    let mut setup = setup().await;
+    let admin = setup.admin.pubkey();
+
+    let ix = start_ix(&setup);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+    let ix = advance_ix(&setup, &admin);
+    send(&mut setup.context, &[&setup.admin], &[ix])
+        .await
+        .unwrap();
+
+    let user = setup.alice.pubkey();
+    let ix = Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::ReleaseFundsToGroup {
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            group_pool: setup.group_pool,
+            group_vault: group_vault(&setup),
+            session: session_address(&setup),
+            member_position: pda(&[b"member_position", setup.group_pool.as_ref(), user.as_ref()]).0,
+            user,
+            oracle_config: pda(&[b"oracle_config"]).0,
+            instructions_sysvar: sysvar::instructions::ID,
+            mint: setup.mint,
+            payer: user,
+            token_program: spl_token::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::ReleaseFundsToGroup {
+            amount: 500,
+            nonce: 0,
+            expiry_slot: u64::MAX,
+        }
+        .data(),
+    };
+    let result = send(&mut setup.context, &[&setup.alice], &[ix]).await;
+
+    // Rejected before the oracle signature is even looked at
+    assert_error(result, 0, ErrorCode::SessionNotOpen);
+}
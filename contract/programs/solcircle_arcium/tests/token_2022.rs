@@ -0,0 +1,205 @@
+// Token-2022 mint tests
+//
+// Loads the compiled program, so build first:
+//   arcium build && SBF_OUT_DIR=target/deploy cargo test -p solcircle_arcium
+
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        permanent_delegate::PermanentDelegate,
+        transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig},
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    },
+};
+use common::{assert_error, fetch, pda, send, token_balance, SetupBuilder, DECIMALS};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solcircle_arcium::{ErrorCode, UserDeposit};
+
+const FEE_BASIS_POINTS: u16 = 100;
+const WALLET_BALANCE: u64 = 100_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    user: Keypair,
+    mint: Pubkey,
+    user_token_account: Pubkey,
+}
+
+/// Token-2022 mint carrying `extension`
+fn mint_with(extension: ExtensionType) -> Account {
+    let space =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[extension])
+            .unwrap();
+    let mut data = vec![0u8; space];
+    let mut state =
+        StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+            .unwrap();
+    match extension {
+        ExtensionType::TransferFeeConfig => {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: u64::MAX.into(),
+                transfer_fee_basis_points: FEE_BASIS_POINTS.into(),
+            };
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee;
+            config.newer_transfer_fee = fee;
+        }
+        ExtensionType::PermanentDelegate => {
+            state
+                .init_extension::<PermanentDelegate>(true)
+                .unwrap()
+                .delegate = Some(Pubkey::new_unique()).try_into().unwrap();
+        }
+        _ => unreachable!(),
+    }
+    state.base = spl_token_2022::state::Mint {
+        decimals: DECIMALS,
+        is_initialized: true,
+        ..Default::default()
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token_2022::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Token-2022 account for a transfer-fee mint
+fn fee_token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+        ExtensionType::TransferFeeAmount,
+    ])
+    .unwrap();
+    let mut data = vec![0u8; space];
+    let mut state =
+        StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data)
+            .unwrap();
+    state.init_extension::<TransferFeeAmount>(true).unwrap();
+    state.base = spl_token_2022::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token_2022::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token_2022::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+async fn setup(extension: ExtensionType) -> Setup {
+    let mut builder = SetupBuilder::new();
+
+    let user = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let user_token_account = Pubkey::new_unique();
+
+    builder.account(mint, mint_with(extension));
+    if extension == ExtensionType::TransferFeeConfig {
+        let (user_deposit, deposit_bump) =
+            pda(&[b"user_deposit", user.pubkey().as_ref(), mint.as_ref()]);
+        let (user_vault, vault_bump) = pda(&[b"user_vault", user.pubkey().as_ref(), mint.as_ref()]);
+
+        builder.program_account(
+            user_deposit,
+            &UserDeposit {
+                owner: user.pubkey(),
+                mint,
+                deposited_amount: 0,
+                oracle_nonce: 0,
+                bump: deposit_bump,
+                vault_bump,
+            },
+        );
+        builder.account(user_vault, fee_token_account(mint, user_vault, 0));
+        builder.account(
+            user_token_account,
+            fee_token_account(mint, user.pubkey(), WALLET_BALANCE),
+        );
+    }
+    builder.wallet(user.pubkey());
+
+    Setup {
+        context: builder.start().await,
+        user,
+        mint,
+        user_token_account,
+    }
+}
+
+#[tokio::test]
+async fn credits_net_amount_for_transfer_fee_mint() {
+    let mut setup = setup(ExtensionType::TransferFeeConfig).await;
+    let user = setup.user.pubkey();
+    let user_deposit = pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0;
+    let user_vault = pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0;
+
+    let ix = Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::DepositToUserPda {
+            user_deposit,
+            user_vault,
+            user_token_account: setup.user_token_account,
+            user,
+            owner: user,
+            mint: setup.mint,
+            token_program: spl_token_2022::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::DepositToUserPda { amount: 10_000 }.data(),
+    };
+    send(&mut setup.context, &[&setup.user], &[ix])
+        .await
+        .unwrap();
+
+    // 1% of the deposit is withheld on the vault as the transfer fee
+    assert_eq!(token_balance(&mut setup.context, user_vault).await, 9_900);
+
+    let deposit: UserDeposit = fetch(&mut setup.context, user_deposit).await;
+    assert_eq!(deposit.deposited_amount, 9_900);
+}
+
+#[tokio::test]
+async fn rejects_permanent_delegate_mint() {
+    let mut setup = setup(ExtensionType::PermanentDelegate).await;
+    let user = setup.user.pubkey();
+
+    let ix = Instruction {
+        program_id: solcircle_arcium::ID,
+        accounts: solcircle_arcium::accounts::InitializeUserDeposit {
+            mint: setup.mint,
+            user_deposit: pda(&[b"user_deposit", user.as_ref(), setup.mint.as_ref()]).0,
+            user_vault: pda(&[b"user_vault", user.as_ref(), setup.mint.as_ref()]).0,
+            user,
+            token_program: spl_token_2022::ID,
+            system_program: solana_sdk::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: solcircle_arcium::instruction::InitializeUserDeposit {}.data(),
+    };
+    let result = send(&mut setup.context, &[&setup.user], &[ix]).await;
+
+    assert_error(result, 0, ErrorCode::UnsupportedMintExtension);
+}
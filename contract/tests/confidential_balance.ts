@@ -0,0 +1,149 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { createMint, createAccount, mintTo } from "@solana/spl-token";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import { randomBytes } from "crypto";
+import {
+  RescueCipher,
+  awaitComputationFinalization,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMXEAccAddress,
+  getMXEPublicKey,
+  getMempoolAccAddress,
+  x25519,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import { initCompDefs, nextMpcRequestAddress } from "./utils";
+
+describe("Confidential balances", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace
+    .SolcircleArcium as Program<SolcircleArcium>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  const user = (provider.wallet as anchor.Wallet).payer;
+  const arciumEnv = getArciumEnv();
+
+  const arciumAccounts = (circuit: string, computationOffset: anchor.BN) => ({
+    computationAccount: getComputationAccAddress(
+      program.programId,
+      computationOffset
+    ),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: getCompDefAccAddress(
+      program.programId,
+      Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+    ),
+  });
+
+  type Event = anchor.IdlEvents<(typeof program)["idl"]>;
+  const awaitEvent = async <E extends keyof Event>(
+    eventName: E
+  ): Promise<Event[E]> => {
+    let listenerId: number;
+    const event = await new Promise<Event[E]>((res) => {
+      listenerId = program.addEventListener(eventName, (event) => {
+        res(event);
+      });
+    });
+    await program.removeEventListener(listenerId);
+
+    return event;
+  };
+
+  before(async () => {
+    await initCompDefs(program, user);
+  });
+
+  it("stores only ciphertext that its owner can decrypt", async () => {
+    const mxePublicKey = await getMXEPublicKey(provider, program.programId);
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(
+      x25519.getSharedSecret(privateKey, mxePublicKey)
+    );
+
+    const mint = await createMint(
+      provider.connection,
+      user,
+      user.publicKey,
+      null,
+      6
+    );
+    const [depositPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_deposit"),
+        user.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+
+    // Also creates the confidential vault for this mint
+    await program.methods
+      .initializeConfidentialDeposit(Array.from(publicKey))
+      .accounts({ user: user.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+    const userTokenAccount = await createAccount(
+      provider.connection,
+      user,
+      mint,
+      user.publicKey
+    );
+    const amount = BigInt(1_234_567);
+    await mintTo(
+      provider.connection,
+      user,
+      mint,
+      userTokenAccount,
+      user,
+      amount
+    );
+
+    const balanceUpdated = awaitEvent("confidentialBalanceUpdated");
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialDeposit(computationOffset, new anchor.BN(amount.toString()))
+      .accountsPartial({
+        confidentialDeposit: depositPda,
+        user: user.publicKey,
+        userTokenAccount,
+        mint,
+        mpcRequest: await nextMpcRequestAddress(program, user.publicKey),
+        ...arciumAccounts("add_to_balance", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+    await balanceUpdated;
+
+    // Nothing in the raw account should give the amount away
+    const info = await provider.connection.getAccountInfo(depositPda);
+    const amountLe = Buffer.alloc(8);
+    amountLe.writeBigUInt64LE(amount);
+    const amountBe = Buffer.alloc(8);
+    amountBe.writeBigUInt64BE(amount);
+    expect(info.data.includes(amountLe)).to.be.false;
+    expect(info.data.includes(amountBe)).to.be.false;
+
+    // ...but the owner can still decrypt it with their x25519 key
+    const deposit = await program.account.confidentialUserDeposit.fetch(
+      depositPda
+    );
+    expect(deposit.balanceInitialized).to.be.true;
+    const nonce = deposit.balanceNonce.toArrayLike(Buffer, "le", 16);
+    const [balance] = cipher.decrypt([deposit.encryptedBalance], nonce);
+    expect(balance).to.equal(amount);
+  });
+});
@@ -0,0 +1,318 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  createMint,
+  createAccount,
+  getAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import { randomBytes } from "crypto";
+import {
+  RescueCipher,
+  awaitComputationFinalization,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMXEAccAddress,
+  getMXEPublicKey,
+  getMempoolAccAddress,
+  x25519,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import {
+  EncryptedAmount,
+  ORACLE_ACTION,
+  initCompDefs,
+  initOracle,
+  nextMpcRequestAddress,
+  oracleSignatureIx,
+} from "./utils";
+
+type Member = {
+  keypair: Keypair;
+  cipher: RescueCipher;
+  publicKey: Uint8Array;
+  deposit: PublicKey;
+  vault: PublicKey;
+};
+
+describe("Confidential settlement", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace
+    .SolcircleArcium as Program<SolcircleArcium>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  // Pool admin and oracle
+  const admin = (provider.wallet as anchor.Wallet).payer;
+  const arciumEnv = getArciumEnv();
+
+  const arciumAccounts = (circuit: string, computationOffset: anchor.BN) => ({
+    computationAccount: getComputationAccAddress(
+      program.programId,
+      computationOffset
+    ),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: getCompDefAccAddress(
+      program.programId,
+      Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+    ),
+  });
+
+  const finalize = (computationOffset: anchor.BN) =>
+    awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+  const tokenBalance = async (account: PublicKey) =>
+    (await getAccount(provider.connection, account, "confirmed")).amount;
+
+  const groupId = `settle-${Date.now()}`;
+  let mint: PublicKey;
+  let pool: PublicKey;
+  let groupVault: PublicKey;
+
+  // Create a member with `amount` in their confidential balance
+  const addMember = async (keypair: Keypair, amount: number) => {
+    const mxePublicKey = await getMXEPublicKey(provider, program.programId);
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const [deposit] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_deposit"),
+        keypair.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    const [vault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_vault"),
+        keypair.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .initializeConfidentialDeposit(Array.from(publicKey))
+      .accounts({ user: keypair.publicKey, mint })
+      .signers([keypair])
+      .rpc({ commitment: "confirmed" });
+    const tokenAccount = await createAccount(
+      provider.connection,
+      admin,
+      mint,
+      keypair.publicKey
+    );
+    await mintTo(provider.connection, admin, mint, tokenAccount, admin, amount);
+
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialDeposit(computationOffset, new anchor.BN(amount))
+      .accountsPartial({
+        confidentialDeposit: deposit,
+        user: keypair.publicKey,
+        userTokenAccount: tokenAccount,
+        mint,
+        mpcRequest: await nextMpcRequestAddress(program, keypair.publicKey),
+        ...arciumAccounts("add_to_balance", computationOffset),
+      })
+      .signers([keypair])
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+
+    return {
+      keypair,
+      cipher: new RescueCipher(
+        x25519.getSharedSecret(privateKey, mxePublicKey)
+      ),
+      publicKey,
+      deposit,
+      vault,
+    };
+  };
+
+  const encryptAmount = (member: Member, amount: number): EncryptedAmount => {
+    const nonce = randomBytes(16);
+    const [ciphertext] = member.cipher.encrypt([BigInt(amount)], nonce);
+    return {
+      encryptionPubkey: Array.from(member.publicKey),
+      nonce: new anchor.BN(nonce, "le"),
+      ciphertext,
+    };
+  };
+
+  // Move `amount` between the member's balance and the pool
+  const release = async (member: Member, toGroup: boolean, amount: number) => {
+    const user = member.keypair.publicKey;
+    const transferAmount = encryptAmount(member, amount);
+    const nonce = (
+      await program.account.confidentialUserDeposit.fetch(member.deposit)
+    ).oracleNonce;
+    const expirySlot = new anchor.BN(
+      (await provider.connection.getSlot("confirmed")) + 1_000
+    );
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    const method = toGroup
+      ? program.methods.confidentialReleaseToGroup
+      : program.methods.confidentialReleaseFromGroup;
+
+    await method(computationOffset, transferAmount, nonce, expirySlot)
+      .accountsPartial({
+        confidentialDeposit: member.deposit,
+        userVault: member.vault,
+        confidentialPool: pool,
+        groupVault,
+        user,
+        payer: user,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        mpcRequest: await nextMpcRequestAddress(program, user),
+        ...arciumAccounts(
+          toGroup
+            ? "confidential_transfer_to_pool"
+            : "confidential_transfer_from_pool",
+          computationOffset
+        ),
+      })
+      .preInstructions([
+        oracleSignatureIx(program.programId, admin, {
+          action: toGroup
+            ? ORACLE_ACTION.confidentialReleaseToGroup
+            : ORACLE_ACTION.confidentialReleaseFromGroup,
+          user,
+          group: pool,
+          mint,
+          amount: transferAmount,
+          nonce,
+          expirySlot,
+        }),
+      ])
+      .signers([member.keypair])
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+  };
+
+  // Anyone can settle a member; the admin cranks both here
+  const settle = async (member: Member) => {
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .settleConfidentialMember(computationOffset)
+      .accountsPartial({
+        confidentialDeposit: member.deposit,
+        userVault: member.vault,
+        confidentialPool: pool,
+        groupVault,
+        user: member.keypair.publicKey,
+        payer: admin.publicKey,
+        mpcRequest: await nextMpcRequestAddress(program, admin.publicKey),
+        ...arciumAccounts("compute_user_share", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+  };
+
+  // Count the pool's members inside the MPC and fetch the result
+  const poolStats = async () => {
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .computePoolStats(computationOffset, new anchor.BN(0))
+      .accountsPartial({
+        confidentialPool: pool,
+        admin: admin.publicKey,
+        mpcRequest: await nextMpcRequestAddress(program, admin.publicKey),
+        ...arciumAccounts("compute_aggregate_stats", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+    return program.account.confidentialGroupPool.fetch(pool);
+  };
+
+  before(async () => {
+    await initCompDefs(program, admin);
+    await initOracle(program, admin);
+
+    mint = await createMint(
+      provider.connection,
+      admin,
+      admin.publicKey,
+      null,
+      6
+    );
+    [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("confidential_pool"), Buffer.from(groupId), mint.toBuffer()],
+      program.programId
+    );
+    [groupVault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_group_vault"),
+        Buffer.from(groupId),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createConfidentialGroupPool(groupId, "Settlement test")
+      .accounts({ admin: admin.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("pays both members their own stake after one withdraws", async () => {
+    const bobKeypair = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        bobKeypair.publicKey,
+        LAMPORTS_PER_SOL
+      ),
+      "confirmed"
+    );
+    const alice = await addMember(admin, 600_000);
+    const bob = await addMember(bobKeypair, 400_000);
+
+    await release(alice, true, 600_000);
+    await release(bob, true, 400_000);
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(1_000_000));
+
+    // Alice takes some back, leaving 400_000 contributed by each member
+    await release(alice, false, 200_000);
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(800_000));
+    expect(await tokenBalance(alice.vault)).to.equal(BigInt(200_000));
+
+    // The pool holds enough, but that would dip into Bob's contribution
+    await release(alice, false, 500_000);
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(800_000));
+    expect(await tokenBalance(alice.vault)).to.equal(BigInt(200_000));
+
+    // Alice still has a stake after her partial withdrawal, so both count
+    let stats = await poolStats();
+    expect(stats.participantCount).to.equal(2);
+    expect(stats.pendingRequest).to.be.null;
+
+    await program.methods
+      .closeConfidentialPool()
+      .accountsPartial({ confidentialPool: pool, admin: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await settle(bob);
+    expect(await tokenBalance(bob.vault)).to.equal(BigInt(400_000));
+    await settle(alice);
+    expect(await tokenBalance(alice.vault)).to.equal(BigInt(600_000));
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(0));
+
+    stats = await poolStats();
+    expect(stats.participantCount).to.equal(0);
+  });
+});
@@ -0,0 +1,339 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
+import {
+  createMint,
+  createAccount,
+  getAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import { randomBytes } from "crypto";
+import {
+  RescueCipher,
+  awaitComputationFinalization,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMXEAccAddress,
+  getMXEPublicKey,
+  getMempoolAccAddress,
+  x25519,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import {
+  EncryptedAmount,
+  ORACLE_ACTION,
+  initCompDefs,
+  initOracle,
+  nextMpcRequestAddress,
+  oracleSignatureIx,
+} from "./utils";
+
+describe("Confidential transfers", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace
+    .SolcircleArcium as Program<SolcircleArcium>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  // Signs as both the member and the oracle
+  const user = (provider.wallet as anchor.Wallet).payer;
+  const arciumEnv = getArciumEnv();
+
+  const [oracleConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("oracle_config")],
+    program.programId
+  );
+
+  const arciumAccounts = (circuit: string, computationOffset: anchor.BN) => ({
+    computationAccount: getComputationAccAddress(
+      program.programId,
+      computationOffset
+    ),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: getCompDefAccAddress(
+      program.programId,
+      Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+    ),
+  });
+
+  let cipher: RescueCipher;
+  let publicKey: Uint8Array;
+  let mint: PublicKey;
+  let depositPda: PublicKey;
+  let userVault: PublicKey;
+  let userTokenAccount: PublicKey;
+
+  // Encrypt `amount` for the MPC, as an `EncryptedAmount` argument
+  const encryptAmount = (amount: bigint): EncryptedAmount => {
+    const nonce = randomBytes(16);
+    const [ciphertext] = cipher.encrypt([amount], nonce);
+    return {
+      encryptionPubkey: Array.from(publicKey),
+      nonce: new anchor.BN(nonce, "le"),
+      ciphertext,
+    };
+  };
+
+  const oracleSignature = (
+    action: number,
+    group: PublicKey,
+    amount: EncryptedAmount,
+    nonce: anchor.BN,
+    expirySlot: anchor.BN
+  ) =>
+    oracleSignatureIx(program.programId, user, {
+      action,
+      user: user.publicKey,
+      group,
+      mint,
+      amount,
+      nonce,
+      expirySlot,
+    });
+
+  const nextOracleNonce = async () =>
+    (await program.account.confidentialUserDeposit.fetch(depositPda))
+      .oracleNonce;
+
+  const expirySlot = async () =>
+    new anchor.BN((await provider.connection.getSlot("confirmed")) + 1_000);
+
+  const tokenBalance = async (account: PublicKey) =>
+    (await getAccount(provider.connection, account, "confirmed")).amount;
+
+  const decryptedBalance = async () => {
+    const deposit = await program.account.confidentialUserDeposit.fetch(
+      depositPda
+    );
+    const nonce = deposit.balanceNonce.toArrayLike(Buffer, "le", 16);
+    return cipher.decrypt([deposit.encryptedBalance], nonce)[0];
+  };
+
+  const finalize = (computationOffset: anchor.BN) =>
+    awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+  const withdraw = async (
+    amount: EncryptedAmount,
+    signed: EncryptedAmount
+  ) => {
+    const nonce = await nextOracleNonce();
+    const expiry = await expirySlot();
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialWithdraw(computationOffset, amount, nonce, expiry)
+      .accountsPartial({
+        confidentialDeposit: depositPda,
+        userVault,
+        userTokenAccount,
+        user: user.publicKey,
+        oracleConfig,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        mpcRequest: await nextMpcRequestAddress(program, user.publicKey),
+        ...arciumAccounts("subtract_from_balance", computationOffset),
+      })
+      .preInstructions([
+        oracleSignature(
+          ORACLE_ACTION.confidentialWithdraw,
+          PublicKey.default,
+          signed,
+          nonce,
+          expiry
+        ),
+      ])
+      .rpc({ commitment: "confirmed" });
+    return computationOffset;
+  };
+
+  before(async () => {
+    await initCompDefs(program, user);
+    await initOracle(program, user);
+
+    const mxePublicKey = await getMXEPublicKey(provider, program.programId);
+    const privateKey = x25519.utils.randomSecretKey();
+    publicKey = x25519.getPublicKey(privateKey);
+    cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+
+    mint = await createMint(
+      provider.connection,
+      user,
+      user.publicKey,
+      null,
+      6
+    );
+    [depositPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_deposit"),
+        user.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    [userVault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_vault"),
+        user.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .initializeConfidentialDeposit(Array.from(publicKey))
+      .accounts({ user: user.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+    userTokenAccount = await createAccount(
+      provider.connection,
+      user,
+      mint,
+      user.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      user,
+      mint,
+      userTokenAccount,
+      user,
+      1_000_000
+    );
+
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialDeposit(computationOffset, new anchor.BN(1_000_000))
+      .accountsPartial({
+        confidentialDeposit: depositPda,
+        user: user.publicKey,
+        userTokenAccount,
+        mint,
+        mpcRequest: await nextMpcRequestAddress(program, user.publicKey),
+        ...arciumAccounts("add_to_balance", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+  });
+
+  it("withdraws exactly the amount the MPC debited", async () => {
+    const before = await tokenBalance(userTokenAccount);
+
+    const amount = encryptAmount(BigInt(400_000));
+    await finalize(await withdraw(amount, amount));
+
+    expect(await tokenBalance(userTokenAccount)).to.equal(
+      before + BigInt(400_000)
+    );
+    expect(await tokenBalance(userVault)).to.equal(BigInt(600_000));
+    expect(await decryptedBalance()).to.equal(BigInt(600_000));
+  });
+
+  it("rejects a ciphertext the oracle didn't sign", async () => {
+    const signed = encryptAmount(BigInt(1));
+    const sent = encryptAmount(BigInt(600_000));
+
+    try {
+      await withdraw(sent, signed);
+      expect.fail("withdrawal should have been rejected");
+    } catch (error) {
+      expect(error.error?.errorCode?.code).to.equal("InvalidOracleSignature");
+    }
+    expect(await tokenBalance(userVault)).to.equal(BigInt(600_000));
+  });
+
+  it("moves nothing when the MPC refuses the withdrawal", async () => {
+    const before = await tokenBalance(userTokenAccount);
+
+    // More than the encrypted balance holds
+    const amount = encryptAmount(BigInt(700_000));
+    await finalize(await withdraw(amount, amount));
+
+    expect(await tokenBalance(userTokenAccount)).to.equal(before);
+    expect(await tokenBalance(userVault)).to.equal(BigInt(600_000));
+    expect(await decryptedBalance()).to.equal(BigInt(600_000));
+  });
+
+  it("moves exactly the revealed amount into and out of a dark pool", async () => {
+    const groupId = `transfers-${Date.now()}`;
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("confidential_pool"), Buffer.from(groupId), mint.toBuffer()],
+      program.programId
+    );
+    const [groupVault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_group_vault"),
+        Buffer.from(groupId),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    await program.methods
+      .createConfidentialGroupPool(groupId, "Transfers test")
+      .accounts({ admin: user.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+
+    const release = async (
+      toGroup: boolean,
+      amount: EncryptedAmount
+    ) => {
+      const nonce = await nextOracleNonce();
+      const expiry = await expirySlot();
+      const computationOffset = new anchor.BN(randomBytes(8), "hex");
+      const method = toGroup
+        ? program.methods.confidentialReleaseToGroup
+        : program.methods.confidentialReleaseFromGroup;
+      await method(computationOffset, amount, nonce, expiry)
+        .accountsPartial({
+          confidentialDeposit: depositPda,
+          userVault,
+          confidentialPool: poolPda,
+          groupVault,
+          user: user.publicKey,
+          payer: user.publicKey,
+          oracleConfig,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          mpcRequest: await nextMpcRequestAddress(program, user.publicKey),
+          ...arciumAccounts(
+            toGroup
+              ? "confidential_transfer_to_pool"
+              : "confidential_transfer_from_pool",
+            computationOffset
+          ),
+        })
+        .preInstructions([
+          oracleSignature(
+            toGroup
+              ? ORACLE_ACTION.confidentialReleaseToGroup
+              : ORACLE_ACTION.confidentialReleaseFromGroup,
+            poolPda,
+            amount,
+            nonce,
+            expiry
+          ),
+        ])
+        .rpc({ commitment: "confirmed" });
+      await finalize(computationOffset);
+    };
+
+    await release(true, encryptAmount(BigInt(250_000)));
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(250_000));
+    expect(await tokenBalance(userVault)).to.equal(BigInt(350_000));
+    expect(await decryptedBalance()).to.equal(BigInt(350_000));
+
+    await release(false, encryptAmount(BigInt(100_000)));
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(150_000));
+    expect(await tokenBalance(userVault)).to.equal(BigInt(450_000));
+    expect(await decryptedBalance()).to.equal(BigInt(450_000));
+
+    // More than the member put in: refused, nothing moves
+    await release(false, encryptAmount(BigInt(200_000)));
+    expect(await tokenBalance(groupVault)).to.equal(BigInt(150_000));
+    expect(await tokenBalance(userVault)).to.equal(BigInt(450_000));
+    expect(await decryptedBalance()).to.equal(BigInt(450_000));
+  });
+});
@@ -0,0 +1,483 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  createMint,
+  createAccount,
+  mintTo,
+  transfer,
+} from "@solana/spl-token";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import { randomBytes } from "crypto";
+import {
+  RescueCipher,
+  awaitComputationFinalization,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMXEAccAddress,
+  getMXEPublicKey,
+  getMempoolAccAddress,
+  x25519,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import {
+  EncryptedAmount,
+  ORACLE_ACTION,
+  initCompDefs,
+  initOracle,
+  nextMpcRequestAddress,
+  oracleSignatureIx,
+} from "./utils";
+
+const VOTING_SLOTS = 30;
+
+type Member = {
+  keypair: Keypair;
+  cipher: RescueCipher;
+  publicKey: Uint8Array;
+  deposit: PublicKey;
+  vault: PublicKey;
+  tokenAccount: PublicKey;
+  position: PublicKey;
+};
+
+describe("Confidential voting", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace
+    .SolcircleArcium as Program<SolcircleArcium>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  // Pool admin, first member and oracle
+  const user = (provider.wallet as anchor.Wallet).payer;
+  const arciumEnv = getArciumEnv();
+
+  const arciumAccounts = (circuit: string, computationOffset: anchor.BN) => ({
+    computationAccount: getComputationAccAddress(
+      program.programId,
+      computationOffset
+    ),
+    clusterAccount: arciumEnv.arciumClusterPubkey,
+    mxeAccount: getMXEAccAddress(program.programId),
+    mempoolAccount: getMempoolAccAddress(program.programId),
+    executingPool: getExecutingPoolAccAddress(program.programId),
+    compDefAccount: getCompDefAccAddress(
+      program.programId,
+      Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+    ),
+  });
+
+  const finalize = (computationOffset: anchor.BN) =>
+    awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+  const groupId = `voting-${Date.now()}`;
+  let mint: PublicKey;
+  let pool: PublicKey;
+  let groupVault: PublicKey;
+  let alice: Member;
+
+  // Move `amount` from the member's token account into their balance
+  const depositToBalance = async (member: Member, amount: number) => {
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialDeposit(computationOffset, new anchor.BN(amount))
+      .accountsPartial({
+        confidentialDeposit: member.deposit,
+        user: member.keypair.publicKey,
+        userTokenAccount: member.tokenAccount,
+        mint,
+        mpcRequest: await nextMpcRequestAddress(
+          program,
+          member.keypair.publicKey
+        ),
+        ...arciumAccounts("add_to_balance", computationOffset),
+      })
+      .signers([member.keypair])
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+  };
+
+  // Create a member with `amount` in their confidential balance
+  const addMember = async (keypair: Keypair, amount: number) => {
+    const mxePublicKey = await getMXEPublicKey(provider, program.programId);
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const [deposit] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_deposit"),
+        keypair.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    const [vault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_vault"),
+        keypair.publicKey.toBuffer(),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+    const [position] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_member"),
+        pool.toBuffer(),
+        keypair.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .initializeConfidentialDeposit(Array.from(publicKey))
+      .accounts({ user: keypair.publicKey, mint })
+      .signers([keypair])
+      .rpc({ commitment: "confirmed" });
+    const tokenAccount = await createAccount(
+      provider.connection,
+      user,
+      mint,
+      keypair.publicKey
+    );
+    const member = {
+      keypair,
+      cipher: new RescueCipher(
+        x25519.getSharedSecret(privateKey, mxePublicKey)
+      ),
+      publicKey,
+      deposit,
+      vault,
+      tokenAccount,
+      position,
+    };
+    if (amount > 0) {
+      await mintTo(provider.connection, user, mint, tokenAccount, user, amount);
+      await depositToBalance(member, amount);
+    }
+    return member;
+  };
+
+  const encrypt = (member: Member, value: bigint): EncryptedAmount => {
+    const nonce = randomBytes(16);
+    const [ciphertext] = member.cipher.encrypt([value], nonce);
+    return {
+      encryptionPubkey: Array.from(member.publicKey),
+      nonce: new anchor.BN(nonce, "le"),
+      ciphertext,
+    };
+  };
+
+  const oracleSigned = async (member: Member) => ({
+    nonce: (
+      await program.account.confidentialUserDeposit.fetch(member.deposit)
+    ).oracleNonce,
+    expirySlot: new anchor.BN(
+      (await provider.connection.getSlot("confirmed")) + 1_000
+    ),
+  });
+
+  // Queue a move between the member's balance and the pool, returning its
+  // computation offset
+  const queueRelease = async (
+    member: Member,
+    toGroup: boolean,
+    amount: number
+  ) => {
+    const transferAmount = encrypt(member, BigInt(amount));
+    const { nonce, expirySlot } = await oracleSigned(member);
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    const method = toGroup
+      ? program.methods.confidentialReleaseToGroup
+      : program.methods.confidentialReleaseFromGroup;
+    await method(computationOffset, transferAmount, nonce, expirySlot)
+      .accountsPartial({
+        confidentialDeposit: member.deposit,
+        userVault: member.vault,
+        confidentialPool: pool,
+        groupVault,
+        user: member.keypair.publicKey,
+        payer: member.keypair.publicKey,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        mpcRequest: await nextMpcRequestAddress(
+          program,
+          member.keypair.publicKey
+        ),
+        ...arciumAccounts(
+          toGroup
+            ? "confidential_transfer_to_pool"
+            : "confidential_transfer_from_pool",
+          computationOffset
+        ),
+      })
+      .preInstructions([
+        oracleSignatureIx(program.programId, user, {
+          action: toGroup
+            ? ORACLE_ACTION.confidentialReleaseToGroup
+            : ORACLE_ACTION.confidentialReleaseFromGroup,
+          user: member.keypair.publicKey,
+          group: pool,
+          mint,
+          amount: transferAmount,
+          nonce,
+          expirySlot,
+        }),
+      ])
+      .signers([member.keypair])
+      .rpc({ commitment: "confirmed" });
+    return computationOffset;
+  };
+
+  // Take `amount` out of the member's balance to their token account
+  const withdraw = async (member: Member, amount: number) => {
+    const withdrawAmount = encrypt(member, BigInt(amount));
+    const { nonce, expirySlot } = await oracleSigned(member);
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .confidentialWithdraw(
+        computationOffset,
+        withdrawAmount,
+        nonce,
+        expirySlot
+      )
+      .accountsPartial({
+        confidentialDeposit: member.deposit,
+        userVault: member.vault,
+        userTokenAccount: member.tokenAccount,
+        user: member.keypair.publicKey,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        mpcRequest: await nextMpcRequestAddress(
+          program,
+          member.keypair.publicKey
+        ),
+        ...arciumAccounts("subtract_from_balance", computationOffset),
+      })
+      .preInstructions([
+        oracleSignatureIx(program.programId, user, {
+          action: ORACLE_ACTION.confidentialWithdraw,
+          user: member.keypair.publicKey,
+          group: PublicKey.default,
+          mint,
+          amount: withdrawAmount,
+          nonce,
+          expirySlot,
+        }),
+      ])
+      .signers([member.keypair])
+      .rpc({ commitment: "confirmed" });
+    await finalize(computationOffset);
+  };
+
+  const createProposal = async (proposalId: number) => {
+    // Positions last written in the proposal's own slot can't vote on it
+    await waitForSlot((await provider.connection.getSlot("confirmed")) + 1);
+    const id = new anchor.BN(proposalId);
+    await program.methods
+      .createConfidentialProposal(
+        id,
+        { buy: {} },
+        mint,
+        new anchor.BN(1_000),
+        new anchor.BN(VOTING_SLOTS),
+        5_000
+      )
+      .accountsPartial({ confidentialPool: pool, proposer: user.publicKey })
+      .rpc({ commitment: "confirmed" });
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_proposal"),
+        pool.toBuffer(),
+        id.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+  };
+
+  const castVote = async (
+    member: Member,
+    proposal: PublicKey,
+    approve: boolean
+  ) => {
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .castConfidentialVote(
+        computationOffset,
+        encrypt(member, BigInt(approve ? 1 : 0))
+      )
+      .accountsPartial({
+        proposal,
+        confidentialMember: member.position,
+        voter: member.keypair.publicKey,
+        mpcRequest: await nextMpcRequestAddress(
+          program,
+          member.keypair.publicKey
+        ),
+        ...arciumAccounts("add_weighted_vote", computationOffset),
+      })
+      .signers([member.keypair])
+      .rpc({ commitment: "confirmed" });
+    return computationOffset;
+  };
+
+  const tally = async (proposal: PublicKey) => {
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .tallyConfidentialVotes(computationOffset)
+      .accountsPartial({
+        proposal,
+        confidentialPool: pool,
+        payer: user.publicKey,
+        mpcRequest: await nextMpcRequestAddress(program, user.publicKey),
+        ...arciumAccounts("tally_votes", computationOffset),
+      })
+      .rpc({ commitment: "confirmed" });
+    return computationOffset;
+  };
+
+  const waitForSlot = async (slot: number) => {
+    while ((await provider.connection.getSlot("confirmed")) < slot) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+  };
+
+  before(async () => {
+    await initCompDefs(program, user);
+    await initOracle(program, user);
+
+    mint = await createMint(
+      provider.connection,
+      user,
+      user.publicKey,
+      null,
+      6
+    );
+    [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("confidential_pool"), Buffer.from(groupId), mint.toBuffer()],
+      program.programId
+    );
+    [groupVault] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("confidential_group_vault"),
+        Buffer.from(groupId),
+        mint.toBuffer(),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .createConfidentialGroupPool(groupId, "Voting test")
+      .accounts({ admin: user.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+
+    alice = await addMember(user, 1_000_000);
+    await finalize(await queueRelease(alice, true, 500_000));
+  });
+
+  it("counts an encrypted vote and tallies the outcome", async () => {
+    const proposal = await createProposal(1);
+    const { deadlineSlot } = await program.account.confidentialProposal.fetch(
+      proposal
+    );
+
+    // Too early to tally while voting is open
+    try {
+      await tally(proposal);
+      expect.fail("tally should have been rejected");
+    } catch (error) {
+      expect(error.error?.errorCode?.code).to.equal("VotingStillOpen");
+    }
+
+    await finalize(await castVote(alice, proposal, true));
+    let account = await program.account.confidentialProposal.fetch(proposal);
+    expect(account.voteCount).to.equal(1);
+    expect(account.pendingRequest).to.be.null;
+    const position = await program.account.confidentialMemberPosition.fetch(
+      alice.position
+    );
+    expect(position.pendingRequest).to.be.null;
+
+    // The vote record makes a second vote fail
+    try {
+      await castVote(alice, proposal, false);
+      expect.fail("second vote should have been rejected");
+    } catch (error) {
+      expect(String(error)).to.contain("already in use");
+    }
+
+    await waitForSlot(deadlineSlot.toNumber());
+    await finalize(await tally(proposal));
+    account = await program.account.confidentialProposal.fetch(proposal);
+    expect(account.status).to.deep.equal({ passed: {} });
+    expect(account.turnoutBps).to.equal(10_000);
+  });
+
+  it("holds the vote while the contribution moves, then refuses it", async () => {
+    const proposal = await createProposal(2);
+
+    // The release holds the member position until its callback
+    const release = await queueRelease(alice, true, 100_000);
+    try {
+      await castVote(alice, proposal, true);
+      expect.fail("vote should have waited for the release");
+    } catch (error) {
+      expect(error.error?.errorCode?.code).to.equal("OperationPending");
+    }
+    await finalize(release);
+
+    // The contribution changed after the proposal opened
+    try {
+      await castVote(alice, proposal, true);
+      expect.fail("vote should have been rejected");
+    } catch (error) {
+      expect(error.error?.errorCode?.code).to.equal("VotingWeightChanged");
+    }
+  });
+
+  it("won't count the same funds twice", async () => {
+    const bobKeypair = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        bobKeypair.publicKey,
+        LAMPORTS_PER_SOL
+      ),
+      "confirmed"
+    );
+    const bob = await addMember(bobKeypair, 0);
+    const proposal = await createProposal(3);
+
+    await finalize(await castVote(alice, proposal, true));
+
+    // Alice takes funds that already voted out of the pool and hands them
+    // to Bob, who puts them back in
+    await finalize(await queueRelease(alice, false, 200_000));
+    await withdraw(alice, 200_000);
+    await transfer(
+      provider.connection,
+      user,
+      alice.tokenAccount,
+      bob.tokenAccount,
+      user,
+      200_000
+    );
+    await depositToBalance(bob, 200_000);
+    await finalize(await queueRelease(bob, true, 200_000));
+
+    try {
+      await castVote(bob, proposal, true);
+      expect.fail("moved funds should not vote again");
+    } catch (error) {
+      expect(error.error?.errorCode?.code).to.equal("VotingWeightChanged");
+    }
+    const account = await program.account.confidentialProposal.fetch(proposal);
+    expect(account.voteCount).to.equal(1);
+  });
+});
@@ -0,0 +1,86 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { createMint } from "@solana/spl-token";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import { randomBytes } from "crypto";
+import {
+  awaitComputationFinalization,
+  getArciumEnv,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMXEAccAddress,
+  getMempoolAccAddress,
+} from "@arcium-hq/client";
+import { expect } from "chai";
+import { initCompDefs, nextMpcRequestAddress } from "./utils";
+
+describe("Dark pool stats", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+  const program = anchor.workspace
+    .SolcircleArcium as Program<SolcircleArcium>;
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+  const admin = (provider.wallet as anchor.Wallet).payer;
+  const arciumEnv = getArciumEnv();
+
+  before(async () => {
+    await initCompDefs(program, admin);
+  });
+
+  it("reveals only pool health and sets meets_minimum_threshold", async () => {
+    const groupId = `stats-${Date.now()}`;
+    const mint = await createMint(
+      provider.connection,
+      admin,
+      admin.publicKey,
+      null,
+      6
+    );
+    const [poolPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("confidential_pool"), Buffer.from(groupId), mint.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createConfidentialGroupPool(groupId, "Stats test")
+      .accounts({ admin: admin.publicKey, mint })
+      .rpc({ commitment: "confirmed" });
+
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .computePoolStats(computationOffset, new anchor.BN(0))
+      .accountsPartial({
+        confidentialPool: poolPda,
+        admin: admin.publicKey,
+        mpcRequest: await nextMpcRequestAddress(program, admin.publicKey),
+        computationAccount: getComputationAccAddress(
+          program.programId,
+          computationOffset
+        ),
+        clusterAccount: arciumEnv.arciumClusterPubkey,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("compute_aggregate_stats")).readUInt32LE()
+        ),
+      })
+      .rpc({ commitment: "confirmed" });
+    await awaitComputationFinalization(
+      provider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+    // An empty pool is "healthy" against a zero minimum, but has too few
+    // participants to meet its threshold
+    const pool = await program.account.confidentialGroupPool.fetch(poolPda);
+    expect(pool.participantCount).to.equal(0);
+    expect(pool.meetsMinimumThreshold).to.be.false;
+    // The callback hands the pool back
+    expect(pool.pendingRequest).to.be.null;
+  });
+});
@@ -0,0 +1,213 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Ed25519Program,
+  PublicKey,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { SolcircleArcium } from "../target/types/solcircle_arcium";
+import {
+  getArciumAccountBaseSeed,
+  getArciumProgAddress,
+  getCompDefAccOffset,
+  getMXEAccAddress,
+  getMXEPublicKey,
+} from "@arcium-hq/client";
+import { createHash } from "crypto";
+import * as fs from "fs";
+
+// Circuits with an `init_<name>_comp_def` instruction, in `Circuit` order
+export const CIRCUITS = [
+  "add_to_balance",
+  "subtract_from_balance",
+  "confidential_transfer_to_pool",
+  "confidential_transfer_from_pool",
+  "verify_minimum_balance",
+  "compute_user_share",
+  "compute_aggregate_stats",
+  "add_weighted_vote",
+  "tally_votes",
+];
+
+export function compDefRegistryAddress(programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("comp_def_registry")],
+    programId
+  )[0];
+}
+
+// Address of the next `MpcRequest` a requester will open, from their
+// request counter (which doesn't exist before their first request)
+export async function nextMpcRequestAddress(
+  program: Program<SolcircleArcium>,
+  requester: PublicKey
+): Promise<PublicKey> {
+  const [counterAddress] = PublicKey.findProgramAddressSync(
+    [Buffer.from("mpc_request_counter"), requester.toBuffer()],
+    program.programId
+  );
+  const counter = await program.account.mpcRequestCounter.fetchNullable(
+    counterAddress
+  );
+  const nextRequest = counter ? counter.nextRequest : new anchor.BN(0);
+
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("mpc_request"),
+      requester.toBuffer(),
+      nextRequest.toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  )[0];
+}
+
+// Initialize every computation definition the registry doesn't list yet
+export async function initCompDefs(
+  program: Program<SolcircleArcium>,
+  payer: anchor.web3.Keypair
+): Promise<void> {
+  const registry = await program.account.compDefRegistry.fetchNullable(
+    compDefRegistryAddress(program.programId)
+  );
+  const initialized = registry ? registry.initialized : 0;
+
+  for (const [index, circuit] of CIRCUITS.entries()) {
+    if (initialized & (1 << index)) {
+      continue;
+    }
+
+    const compDefAccount = PublicKey.findProgramAddressSync(
+      [
+        getArciumAccountBaseSeed("ComputationDefinitionAccount"),
+        program.programId.toBuffer(),
+        getCompDefAccOffset(circuit),
+      ],
+      getArciumProgAddress()
+    )[0];
+
+    const method = `init${circuit
+      .split("_")
+      .map((word) => word[0].toUpperCase() + word.slice(1))
+      .join("")}CompDef`;
+    await program.methods[method]()
+      .accounts({
+        compDefAccount,
+        payer: payer.publicKey,
+        mxeAccount: getMXEAccAddress(program.programId),
+      })
+      .signers([payer])
+      .rpc({ commitment: "confirmed" });
+  }
+}
+
+// `OracleAction` discriminants for the confidential instructions
+export const ORACLE_ACTION = {
+  confidentialWithdraw: 3,
+  confidentialReleaseToGroup: 4,
+  confidentialReleaseFromGroup: 5,
+};
+
+export type EncryptedAmount = {
+  encryptionPubkey: number[];
+  nonce: anchor.BN;
+  ciphertext: number[];
+};
+
+// Set up the oracle config with `oracle` as its only key, unless another
+// test file already did
+export async function initOracle(
+  program: Program<SolcircleArcium>,
+  oracle: anchor.web3.Keypair
+): Promise<void> {
+  const [oracleConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("oracle_config")],
+    program.programId
+  );
+  if (await program.account.oracleConfig.fetchNullable(oracleConfig)) {
+    return;
+  }
+
+  await program.methods
+    .initializeOracle({ localnet: {} })
+    .accounts({ authority: oracle.publicKey, oraclePubkey: oracle.publicKey })
+    .signers([oracle])
+    .rpc({ commitment: "confirmed" });
+}
+
+// Same hash as `EncryptedAmount::commitment`
+export function encryptedAmountCommitment(amount: EncryptedAmount): Buffer {
+  return createHash("sha256")
+    .update(Buffer.from(amount.encryptionPubkey))
+    .update(amount.nonce.toArrayLike(Buffer, "le", 16))
+    .update(Buffer.from(amount.ciphertext))
+    .digest();
+}
+
+// Ed25519 instruction in which `oracle` signs the Borsh encoding of an
+// `OracleAuthorization` for a confidential action on Localnet
+export function oracleSignatureIx(
+  programId: PublicKey,
+  oracle: anchor.web3.Keypair,
+  authorization: {
+    action: number;
+    user: PublicKey;
+    group: PublicKey;
+    mint: PublicKey;
+    amount: EncryptedAmount;
+    nonce: anchor.BN;
+    expirySlot: anchor.BN;
+  }
+): TransactionInstruction {
+  const message = Buffer.concat([
+    Buffer.from("solcircle:oracle"),
+    programId.toBuffer(),
+    Buffer.from([0, authorization.action]),
+    authorization.user.toBuffer(),
+    authorization.group.toBuffer(),
+    authorization.mint.toBuffer(),
+    Buffer.from([1]),
+    encryptedAmountCommitment(authorization.amount),
+    authorization.nonce.toArrayLike(Buffer, "le", 8),
+    authorization.expirySlot.toArrayLike(Buffer, "le", 8),
+  ]);
+  return Ed25519Program.createInstructionWithPrivateKey({
+    privateKey: oracle.secretKey,
+    message,
+  });
+}
+
+export async function getMXEPublicKeyWithRetry(
+  provider: anchor.AnchorProvider,
+  programId: PublicKey,
+  maxRetries: number = 10,
+  retryDelayMs: number = 500
+): Promise<Uint8Array> {
+  for (let attempt = 1; attempt <= maxRetries; attempt++) {
+    try {
+      const mxePublicKey = await getMXEPublicKey(provider, programId);
+      if (mxePublicKey) {
+        return mxePublicKey;
+      }
+    } catch (error) {
+      console.log(`Attempt ${attempt} failed to fetch MXE public key:`, error);
+    }
+
+    if (attempt < maxRetries) {
+      console.log(
+        `Retrying in ${retryDelayMs}ms... (attempt ${attempt}/${maxRetries})`
+      );
+      await new Promise((resolve) => setTimeout(resolve, retryDelayMs));
+    }
+  }
+
+  throw new Error(
+    `Failed to fetch MXE public key after ${maxRetries} attempts`
+  );
+}
+
+export function readKpJson(path: string): anchor.web3.Keypair {
+  const file = fs.readFileSync(path);
+  return anchor.web3.Keypair.fromSecretKey(
+    new Uint8Array(JSON.parse(file.toString()))
+  );
+}